@@ -0,0 +1,44 @@
+// Renders a sketch to a PNG file without a browser:
+//
+//     cargo run --example render_png -- <sketch> <out.png> [width height [time]]
+
+use std::env;
+use std::fs;
+use std::process;
+
+use sketchbook_ng::sketches::render_png;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if args.len() < 2 {
+        eprintln!("usage: render_png <sketch> <out.png> [width height [time]]");
+        process::exit(2);
+    }
+
+    let number = |index: usize, default: f64| -> f64 {
+        args.get(index).map_or(default, |arg| {
+            arg.parse().unwrap_or_else(|_| {
+                eprintln!("not a number: {}", arg);
+                process::exit(2);
+            })
+        })
+    };
+
+    let width = number(2, 800.0) as usize;
+    let height = number(3, 600.0) as usize;
+    let time = number(4, 0.0);
+
+    let png = match render_png(&args[0], width, height, time) {
+        Some(png) => png,
+        None => {
+            eprintln!("unknown sketch: {}", args[0]);
+            process::exit(1);
+        }
+    };
+
+    if let Err(error) = fs::write(&args[1], png) {
+        eprintln!("could not write {}: {}", args[1], error);
+        process::exit(1);
+    }
+}
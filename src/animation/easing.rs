@@ -0,0 +1,84 @@
+use std::f64::consts::PI;
+
+// Shapes the progress between two keyframes. Every easing maps 0 to 0 and
+// 1 to 1, though some overshoot in between.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Easing {
+    Linear,
+    // Holds the previous value until the keyframe is reached
+    Step,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    // Overshoots a little before settling
+    BackOut,
+}
+
+impl Easing {
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::Step => if t < 1.0 { 0.0 } else { 1.0 },
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 }
+            }
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 }
+            }
+            Easing::SineIn => 1.0 - f64::cos(t * PI / 2.0),
+            Easing::SineOut => f64::sin(t * PI / 2.0),
+            Easing::SineInOut => (1.0 - f64::cos(t * PI)) / 2.0,
+            Easing::BackOut => {
+                let overshoot = 1.70158;
+                let u = t - 1.0;
+
+                1.0 + (overshoot + 1.0) * u * u * u + overshoot * u * u
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_easing() {
+        let all = [
+            Easing::Linear,
+            Easing::Step,
+            Easing::QuadIn,
+            Easing::QuadOut,
+            Easing::QuadInOut,
+            Easing::CubicIn,
+            Easing::CubicOut,
+            Easing::CubicInOut,
+            Easing::SineIn,
+            Easing::SineOut,
+            Easing::SineInOut,
+            Easing::BackOut,
+        ];
+
+        for easing in &all {
+            assert!(easing.apply(0.0).abs() < 1e-12, "{:?}", easing);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-12, "{:?}", easing);
+        }
+
+        assert_eq!(Easing::QuadIn.apply(0.5), 0.25);
+        assert!((Easing::SineInOut.apply(0.5) - 0.5).abs() < 1e-12);
+        assert!(Easing::BackOut.apply(0.8) > 1.0);
+        assert_eq!(Easing::Step.apply(0.99), 0.0);
+    }
+}
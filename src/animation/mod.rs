@@ -0,0 +1,366 @@
+use crate::common::colors::Color;
+use crate::math::vec3::Vec3;
+use crate::plotter::Plotter;
+use crate::plotter::backend::Backend;
+use crate::plotter::group::GroupId;
+use crate::plotter::scene::PrimitiveHandle;
+use crate::plotter::style::{Fill, FillRule, LineWidth};
+
+use track::{Pose, Track};
+
+pub mod easing;
+pub mod timestep;
+pub mod track;
+
+// Property of a primitive or group driven by a track. Properties the shape
+// does not have, e.g. the position of a polygon, are left alone.
+pub enum Channel {
+    // Point, center of circles, arcs and ellipses, or anchor of text
+    Position(PrimitiveHandle, Track<Vec3>),
+    // One of the points of a shape, see Shape::vertex_mut
+    Vertex(PrimitiveHandle, usize, Track<Vec3>),
+    Stroke(PrimitiveHandle, Track<Color>),
+    // Fills with the nonzero rule unless the primitive had a fill already
+    Fill(PrimitiveHandle, Track<Color>),
+    // Keeps the unit of the current width
+    Width(PrimitiveHandle, Track<f64>),
+    Transform(GroupId, Track<Pose>),
+}
+
+impl Channel {
+    pub fn duration(&self) -> f64 {
+        match self {
+            Channel::Position(_, track) | Channel::Vertex(_, _, track) => track.duration(),
+            Channel::Stroke(_, track) | Channel::Fill(_, track) => track.duration(),
+            Channel::Width(_, track) => track.duration(),
+            Channel::Transform(_, track) => track.duration(),
+        }
+    }
+
+    fn apply<B: Backend>(&self, time: f64, plotter: &mut Plotter<B>) {
+        if let Channel::Transform(id, track) = self {
+            plotter.set_group_transform(*id, track.sample(time).to_mat3());
+            return;
+        }
+
+        let handle = match self {
+            Channel::Position(handle, _)
+            | Channel::Vertex(handle, _, _)
+            | Channel::Stroke(handle, _)
+            | Channel::Fill(handle, _)
+            | Channel::Width(handle, _) => *handle,
+            Channel::Transform(..) => unreachable!(),
+        };
+
+        // Removed primitives are skipped
+        let primitive = match plotter.get_mut(handle) {
+            Some(primitive) => primitive,
+            None => return,
+        };
+
+        match self {
+            Channel::Position(_, track) => {
+                if let Some(position) = primitive.shape.position_mut() {
+                    *position = track.sample(time);
+                }
+            }
+            Channel::Vertex(_, index, track) => {
+                if let Some(vertex) = primitive.shape.vertex_mut(*index) {
+                    *vertex = track.sample(time);
+                }
+            }
+            Channel::Stroke(_, track) => primitive.stroke = Some(track.sample(time)),
+            Channel::Fill(_, track) => {
+                let color = track.sample(time);
+
+                match &mut primitive.fill {
+                    Some(fill) => fill.color = color,
+                    None => primitive.fill = Some(Fill { color, rule: FillRule::NonZero }),
+                }
+            }
+            Channel::Width(_, track) => {
+                let width = track.sample(time);
+
+                primitive.style.width = match primitive.style.width {
+                    LineWidth::Screen(_) => LineWidth::Screen(width),
+                    LineWidth::World(_) => LineWidth::World(width),
+                };
+            }
+            Channel::Transform(..) => (),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Repeat {
+    Once,
+    // Number of passes through the track, so Times(1) is the same as Once
+    Times(u32),
+    Forever,
+}
+
+// Channel placed on a timeline
+pub struct Clip {
+    start: f64,
+    channel: Channel,
+    repeat: Repeat,
+    // Every other pass plays the track backwards
+    ping_pong: bool,
+}
+
+impl Clip {
+    pub fn repeat(&mut self, repeat: Repeat) -> &mut Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn ping_pong(&mut self) -> &mut Self {
+        self.ping_pong = true;
+        self
+    }
+
+    pub fn start(&self) -> f64 {
+        self.start
+    }
+
+    // Infinite when repeated forever
+    pub fn duration(&self) -> f64 {
+        match self.repeat {
+            Repeat::Once => self.channel.duration(),
+            Repeat::Times(passes) => self.channel.duration() * passes.max(1) as f64,
+            Repeat::Forever => f64::INFINITY,
+        }
+    }
+
+    pub fn end(&self) -> f64 {
+        self.start + self.duration()
+    }
+
+    // Time within the track at a time of the timeline. Clips that are over
+    // hold the end of their last pass.
+    fn local_time(&self, time: f64) -> f64 {
+        let length = self.channel.duration();
+
+        if length <= 0.0 {
+            return 0.0;
+        }
+
+        let elapsed = (time - self.start).min(self.duration());
+        let mut pass = (elapsed / length).floor();
+        let mut within = elapsed - pass * length;
+
+        // The end of the last pass rather than the start of the next one
+        if pass > 0.0 && within == 0.0 && elapsed >= self.duration() {
+            pass -= 1.0;
+            within = length;
+        }
+
+        if self.ping_pong && pass % 2.0 == 1.0 {
+            length - within
+        } else {
+            within
+        }
+    }
+}
+
+// Clips played against a clock, in seconds. Clips that have not started
+// leave their property alone, clips that are over hold their final value,
+// and of the clips that drive the same property the one that started last
+// wins.
+pub struct Timeline {
+    clips: Vec<Clip>,
+    // Clock time of the start of the timeline, see update
+    origin: Option<f64>,
+    // Time of the last update, since the origin
+    last: Option<f64>,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Timeline {
+            clips: Vec::new(),
+            origin: None,
+            last: None,
+        }
+    }
+
+    pub fn at(&mut self, start: f64, channel: Channel) -> &mut Clip {
+        self.clips.push(Clip {
+            start,
+            channel,
+            repeat: Repeat::Once,
+            ping_pong: false,
+        });
+
+        self.clips.last_mut().unwrap()
+    }
+
+    // Starts once the clip added last is over
+    pub fn then(&mut self, channel: Channel) -> &mut Clip {
+        let start = self.clips.last().map_or(0.0, Clip::end);
+        self.at(start, channel)
+    }
+
+    // Starts along with the clip added last
+    pub fn with(&mut self, channel: Channel) -> &mut Clip {
+        let start = self.clips.last().map_or(0.0, Clip::start);
+        self.at(start, channel)
+    }
+
+    // Infinite when any clip repeats forever
+    pub fn duration(&self) -> f64 {
+        self.clips.iter().map(Clip::end).fold(0.0, f64::max)
+    }
+
+    pub fn is_finished(&self, time: f64) -> bool {
+        time >= self.duration()
+    }
+
+    // Sets every property driven by the timeline to its value at `time`
+    pub fn apply<B: Backend>(&self, time: f64, plotter: &mut Plotter<B>) {
+        let mut started: Vec<&Clip> = self.clips
+            .iter()
+            .filter(|clip| clip.start <= time)
+            .collect();
+
+        started.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap());
+
+        for clip in started {
+            clip.channel.apply(clip.local_time(time), plotter);
+        }
+    }
+
+    // Plays the timeline from the first clock time it is given, e.g. the
+    // time of tick events. Once finished, the final values are applied a
+    // single time, so that the plotter has nothing left to redraw.
+    pub fn update<B: Backend>(&mut self, now: f64, plotter: &mut Plotter<B>) {
+        let time = now - *self.origin.get_or_insert(now);
+
+        if self.is_finished(time) && self.last.is_some_and(|last| self.is_finished(last)) {
+            return;
+        }
+
+        self.apply(time, plotter);
+        self.last = Some(time);
+    }
+
+    // Starts over on the next update
+    pub fn restart(&mut self) {
+        self.origin = None;
+        self.last = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::easing::Easing;
+    use crate::math::mat3::Mat3;
+    use crate::plotter::backend::recording::RecordingBackend;
+    use crate::plotter::Shape;
+    use crate::plotter::test_support::{plotter, primitive};
+    use crate::vec3;
+
+    fn point(plotter: &mut Plotter<RecordingBackend>) -> PrimitiveHandle {
+        plotter.add_primitive(primitive(Shape::Point(vec3!(0.0, 0.0, 1.0))))
+    }
+
+    fn position(plotter: &Plotter<RecordingBackend>, handle: PrimitiveHandle) -> Vec3 {
+        match plotter.get(handle).unwrap().shape {
+            Shape::Point(point) => point,
+            _ => unreachable!(),
+        }
+    }
+
+    fn slide(handle: PrimitiveHandle, from: f64, to: f64) -> Channel {
+        let track = Track::new(vec3!(from, 0.0, 1.0)).then(1.0, vec3!(to, 0.0, 1.0), Easing::Linear);
+        Channel::Position(handle, track)
+    }
+
+    #[test]
+    fn test_sequence() {
+        let mut plotter = plotter();
+        let handle = point(&mut plotter);
+        let mut timeline = Timeline::new();
+
+        timeline.at(1.0, slide(handle, 0.0, 10.0));
+        timeline.then(slide(handle, 10.0, 30.0));
+        timeline.with(Channel::Stroke(
+            handle,
+            Track::new(Color::rgb(0.0, 0.0, 0.0)).then(2.0, Color::rgb(1.0, 0.0, 0.0), Easing::Step),
+        ));
+
+        assert_eq!(timeline.duration(), 4.0);
+
+        // Nothing has started yet
+        timeline.apply(0.5, &mut plotter);
+        assert_eq!(position(&plotter, handle), vec3!(0.0, 0.0, 1.0));
+
+        timeline.apply(1.5, &mut plotter);
+        assert_eq!(position(&plotter, handle), vec3!(5.0, 0.0, 1.0));
+
+        timeline.apply(2.5, &mut plotter);
+        assert_eq!(position(&plotter, handle), vec3!(20.0, 0.0, 1.0));
+        assert_eq!(plotter.get(handle).unwrap().stroke, Some(Color::rgb(0.0, 0.0, 0.0)));
+
+        timeline.apply(10.0, &mut plotter);
+        assert_eq!(position(&plotter, handle), vec3!(30.0, 0.0, 1.0));
+        assert_eq!(plotter.get(handle).unwrap().stroke, Some(Color::rgb(1.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn test_repeat() {
+        let mut plotter = plotter();
+        let handle = point(&mut plotter);
+        let mut timeline = Timeline::new();
+
+        timeline.at(0.0, slide(handle, 0.0, 10.0)).repeat(Repeat::Times(3)).ping_pong();
+
+        let expected = [(0.25, 2.5), (1.25, 7.5), (2.25, 2.5), (2.0, 0.0), (3.0, 10.0), (5.0, 10.0)];
+
+        for &(time, x) in &expected {
+            timeline.apply(time, &mut plotter);
+            assert_eq!(position(&plotter, handle).x, x, "{}", time);
+        }
+
+        // Ends on the way back
+        timeline.clips[0].repeat(Repeat::Times(2));
+        timeline.apply(5.0, &mut plotter);
+        assert_eq!(position(&plotter, handle).x, 0.0);
+
+        timeline.clips[0].repeat(Repeat::Forever);
+        timeline.apply(100.5, &mut plotter);
+        assert_eq!(position(&plotter, handle).x, 5.0);
+        assert!(!timeline.is_finished(1e9));
+    }
+
+    #[test]
+    fn test_update_and_transform() {
+        let mut plotter = plotter();
+        let group = plotter.add_group(None, Mat3::identity());
+        let mut timeline = Timeline::new();
+
+        timeline.at(0.0, Channel::Transform(
+            group,
+            Track::new(Pose::new(vec3!(0.0, 0.0, 1.0), 0.0, 1.0))
+                .then(2.0, Pose::new(vec3!(20.0, 0.0, 1.0), 0.0, 3.0), Easing::Linear),
+        ));
+
+        // The first update is the start of the timeline
+        timeline.update(100.0, &mut plotter);
+        timeline.update(101.0, &mut plotter);
+
+        let expected = Pose::new(vec3!(10.0, 0.0, 1.0), 0.0, 2.0).to_mat3();
+        assert!(plotter.group_transform(group).unwrap().close_to(&expected, 1e-12));
+
+        timeline.restart();
+        timeline.update(200.0, &mut plotter);
+        assert!(plotter.group_transform(group).unwrap().close_to(&Mat3::identity(), 1e-12));
+
+        // A finished timeline leaves the plotter alone after applying its end
+        timeline.update(205.0, &mut plotter);
+        plotter.render();
+        timeline.update(206.0, &mut plotter);
+        assert!(!plotter.needs_redraw());
+    }
+}
@@ -0,0 +1,88 @@
+// Splits frame deltas into steps of a fixed length, so that simulations
+// give the same result whatever the frame rate. Time that does not add up
+// to a whole step carries over to the next frame.
+pub struct FixedTimestep {
+    // Seconds per step
+    pub step: f64,
+    // Most steps run for a single frame. Time beyond them is dropped, so
+    // that a slow simulation slows down rather than falling further behind.
+    pub max_steps: u32,
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f64) -> Self {
+        FixedTimestep {
+            step,
+            max_steps: 8,
+            accumulator: 0.0,
+        }
+    }
+
+    // Number of steps to run for a frame that took `delta` seconds
+    pub fn advance(&mut self, delta: f64) -> u32 {
+        if self.step <= 0.0 {
+            return 0;
+        }
+
+        self.accumulator += delta.max(0.0);
+
+        let steps = (self.accumulator / self.step).floor().min(self.max_steps as f64);
+        self.accumulator -= steps * self.step;
+
+        if self.accumulator >= self.step {
+            self.accumulator %= self.step;
+        }
+
+        steps as u32
+    }
+
+    // Runs `update` with the step length for every step of the frame
+    pub fn update<F: FnMut(f64)>(&mut self, delta: f64, mut update: F) {
+        for _ in 0..self.advance(delta) {
+            update(self.step);
+        }
+    }
+
+    // How far the time is into the next step, from 0 to 1, to blend the
+    // last two states of the simulation when drawing
+    pub fn alpha(&self) -> f64 {
+        if self.step > 0.0 { self.accumulator / self.step } else { 0.0 }
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_timestep() {
+        let mut timestep = FixedTimestep::new(0.25);
+
+        assert_eq!(timestep.advance(0.1), 0);
+        assert_eq!(timestep.advance(0.2), 1);
+        assert!((timestep.alpha() - 0.2).abs() < 1e-12);
+
+        let mut steps = Vec::new();
+        timestep.update(0.5, |step| steps.push(step));
+        assert_eq!(steps, vec![0.25, 0.25]);
+
+        // Falling far behind runs a bounded number of steps
+        timestep.max_steps = 4;
+        assert_eq!(timestep.advance(10.0), 4);
+        assert!(timestep.alpha() < 1.0);
+
+        // The same total time gives the same steps, however it is split
+        let mut even = FixedTimestep::new(0.125);
+        let mut uneven = FixedTimestep::new(0.125);
+        let even_steps: u32 = [0.25, 0.25, 0.25, 0.25].iter().map(|&delta| even.advance(delta)).sum();
+        let uneven_steps: u32 = [0.0625, 0.4375, 0.5].iter().map(|&delta| uneven.advance(delta)).sum();
+
+        assert_eq!((even_steps, even.alpha()), (8, 0.0));
+        assert_eq!((uneven_steps, uneven.alpha()), (8, 0.0));
+    }
+}
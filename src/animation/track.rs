@@ -0,0 +1,164 @@
+use crate::common::colors::Color;
+use crate::math::mat3::Mat3;
+use crate::math::vec3::Vec3;
+use crate::vec3;
+
+use super::easing::Easing;
+
+// Values that can be blended between keyframes
+pub trait Tween: Clone {
+    fn tween(&self, other: &Self, t: f64) -> Self;
+}
+
+impl Tween for f64 {
+    fn tween(&self, other: &Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Tween for Vec3 {
+    fn tween(&self, other: &Self, t: f64) -> Self {
+        vec3!(
+            self.x.tween(&other.x, t),
+            self.y.tween(&other.y, t),
+            self.z.tween(&other.z, t)
+        )
+    }
+}
+
+impl Tween for Color {
+    fn tween(&self, other: &Self, t: f64) -> Self {
+        self.lerp(other, t)
+    }
+}
+
+// Transform split into parts that blend well. Blending matrices directly
+// would shrink anything that turns along the way.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Pose {
+    pub translation: Vec3,
+    // Radians, in the sense of Mat3::rotate
+    pub rotation: f64,
+    pub scale: f64,
+}
+
+impl Pose {
+    pub fn new(translation: Vec3, rotation: f64, scale: f64) -> Self {
+        Pose { translation, rotation, scale }
+    }
+
+    // Scales first, then rotates, then translates
+    pub fn to_mat3(&self) -> Mat3 {
+        Mat3::identity()
+            .translate(&self.translation)
+            .rotate(self.rotation)
+            .scale(&vec3!(self.scale, self.scale, 1.0))
+    }
+}
+
+impl Tween for Pose {
+    fn tween(&self, other: &Self, t: f64) -> Self {
+        Pose {
+            translation: self.translation.tween(&other.translation, t),
+            rotation: self.rotation.tween(&other.rotation, t),
+            scale: self.scale.tween(&other.scale, t),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Keyframe<T> {
+    // Seconds from the start of the track
+    pub time: f64,
+    pub value: T,
+    // How the value gets here from the previous keyframe
+    pub easing: Easing,
+}
+
+// Keyframes in time order. Before the first and after the last keyframe
+// the track holds their values.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Tween> Track<T> {
+    // Starts at `value` at time 0
+    pub fn new(value: T) -> Self {
+        Track {
+            keyframes: vec![Keyframe { time: 0.0, value, easing: Easing::Linear }],
+        }
+    }
+
+    // Reaches `value` at `time`. Keyframes are kept in time order whatever
+    // order they are added in.
+    pub fn key(mut self, time: f64, value: T, easing: Easing) -> Self {
+        let index = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        self.keyframes.insert(index, Keyframe { time, value, easing });
+        self
+    }
+
+    // Reaches `value` `duration` seconds after the last keyframe
+    pub fn then(self, duration: f64, value: T, easing: Easing) -> Self {
+        let time = self.duration() + duration.max(0.0);
+        self.key(time, value, easing)
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    pub fn sample(&self, time: f64) -> T {
+        let next = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+
+        if next == 0 {
+            return self.keyframes[0].value.clone();
+        }
+
+        if next == self.keyframes.len() {
+            return self.keyframes[next - 1].value.clone();
+        }
+
+        let from = &self.keyframes[next - 1];
+        let to = &self.keyframes[next];
+        let progress = (time - from.time) / (to.time - from.time);
+
+        from.value.tween(&to.value, to.easing.apply(progress))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample() {
+        let track = Track::new(0.0)
+            .then(1.0, 10.0, Easing::Linear)
+            .then(1.0, 0.0, Easing::QuadIn)
+            .key(0.5, 100.0, Easing::Step);
+
+        assert_eq!(track.duration(), 2.0);
+        assert_eq!(track.sample(-1.0), 0.0);
+        assert_eq!(track.sample(0.25), 0.0);
+        assert_eq!(track.sample(0.5), 100.0);
+        assert_eq!(track.sample(0.75), 55.0);
+        assert_eq!(track.sample(1.5), 7.5);
+        assert_eq!(track.sample(5.0), 0.0);
+    }
+
+    #[test]
+    fn test_pose() {
+        let start = Pose::new(vec3!(0.0, 0.0, 1.0), 0.0, 1.0);
+        let end = Pose::new(vec3!(10.0, 0.0, 1.0), std::f64::consts::PI, 1.0);
+
+        // Halfway through a half turn is a quarter turn, at full size
+        let halfway = start.tween(&end, 0.5).to_mat3();
+        let expected = Mat3::identity()
+            .translate(&vec3!(5.0, 0.0, 1.0))
+            .rotate(std::f64::consts::PI / 2.0);
+
+        assert!(halfway.close_to(&expected, 1e-12));
+        assert!((halfway.linear_scale() - 1.0).abs() < 1e-12);
+    }
+}
@@ -1,10 +1,70 @@
-use crate::math::vec3::{ Vec3 };
-use crate::{ vec3 };
-
-pub const RED: Vec3 = vec3!(0.9, 0.1, 0.0);
-pub const GREEN: Vec3 = vec3!(0.1, 0.7, 0.0);
-pub const AQUA: Vec3 = vec3!(0.007, 0.69, 0.710);
-pub const LIGHT_AQUA: Vec3 = vec3!(0.498, 0.933, 0.940);
-pub const LIGHT_BLUE_INK: Vec3 = vec3!(0.805, 0.860, 0.970);
-pub const BLUE_INK: Vec3 = vec3!(0.498, 0.645, 0.940);
-pub const PAPER: Vec3 = vec3!(0.990, 0.981, 0.881);
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Color {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64,
+}
+
+impl Color {
+    pub const fn rgb(r: f64, g: f64, b: f64) -> Self {
+        Color { r, g, b, a: 1.0 }
+    }
+
+    pub const fn rgba(r: f64, g: f64, b: f64, a: f64) -> Self {
+        Color { r, g, b, a }
+    }
+
+    pub fn with_alpha(&self, a: f64) -> Self {
+        Color { a, ..*self }
+    }
+
+    pub fn lerp(&self, other: &Color, t: f64) -> Self {
+        Color {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
+        }
+    }
+
+    pub fn to_rgba8(self) -> [u8; 4] {
+        let channel = |value: f64| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [channel(self.r), channel(self.g), channel(self.b), channel(self.a)]
+    }
+
+    pub fn as_css_string(&self) -> String {
+        let [r, g, b, a] = self.to_rgba8();
+
+        if a == 255 {
+            format!("rgb({}, {}, {})", r, g, b)
+        } else {
+            format!("rgba({}, {}, {}, {:.3})", r, g, b, (a as f64) / 255.0)
+        }
+    }
+}
+
+pub const RED: Color = Color::rgb(0.9, 0.1, 0.0);
+pub const GREEN: Color = Color::rgb(0.1, 0.7, 0.0);
+pub const AQUA: Color = Color::rgb(0.007, 0.69, 0.710);
+pub const LIGHT_AQUA: Color = Color::rgb(0.498, 0.933, 0.940);
+pub const LIGHT_BLUE_INK: Color = Color::rgb(0.805, 0.860, 0.970);
+pub const BLUE_INK: Color = Color::rgb(0.498, 0.645, 0.940);
+pub const PAPER: Color = Color::rgb(0.990, 0.981, 0.881);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_css_string() {
+        assert_eq!(Color::rgb(1.0, 0.5, 0.0).as_css_string(), "rgb(255, 128, 0)");
+        assert_eq!(Color::rgba(0.0, 0.0, 1.0, 0.5).as_css_string(), "rgba(0, 0, 255, 0.502)");
+    }
+
+    #[test]
+    fn test_lerp() {
+        let blended = Color::rgb(0.0, 0.0, 0.0).lerp(&Color::rgba(1.0, 0.5, 0.0, 0.0), 0.5);
+        assert_eq!(blended, Color::rgba(0.5, 0.25, 0.0, 0.5));
+    }
+}
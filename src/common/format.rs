@@ -0,0 +1,20 @@
+// Shortest decimal for coordinates in text formats, to the thousandth
+pub fn number(value: f64) -> String {
+    let formatted = format!("{:.3}", value);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+
+    if trimmed == "-0" { "0".into() } else { trimmed.into() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number() {
+        assert_eq!(number(12.0), "12");
+        assert_eq!(number(-0.5), "-0.5");
+        assert_eq!(number(1.23456), "1.235");
+        assert_eq!(number(-0.0001), "0");
+    }
+}
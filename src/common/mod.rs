@@ -1 +1,3 @@
 pub mod colors;
+pub mod format;
+pub mod png;
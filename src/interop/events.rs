@@ -1,14 +1,87 @@
 use std::borrow::{BorrowMut};
 use std::cell::{RefCell};
 
+// Longest delta handed to sketches, in seconds. Frames stop while the tab
+// is hidden, and the first one after would otherwise cover the whole time.
+pub const MAX_TICK_DELTA: f64 = 0.1;
+
 pub struct TickEventData {
+    // Clock time in seconds
     pub time: f64,
+    // Seconds since the previous tick, at most MAX_TICK_DELTA, and 0 for
+    // the first one
+    pub delta: f64,
+    // Number of ticks before this one
+    pub frame: u64,
+}
+
+// Turns the clock times of animation frames into tick events
+pub struct FrameClock {
+    last: Option<f64>,
+    frame: u64,
+}
+
+impl FrameClock {
+    pub fn new() -> Self {
+        FrameClock {
+            last: None,
+            frame: 0,
+        }
+    }
+
+    pub fn tick(&mut self, time: f64) -> TickEventData {
+        let delta = self.last.map_or(0.0, |last| (time - last).clamp(0.0, MAX_TICK_DELTA));
+        let data = TickEventData { time, delta, frame: self.frame };
+
+        self.last = Some(time);
+        self.frame += 1;
+        data
+    }
+}
+
+// Keys held down during a pointer or wheel event
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub meta: bool,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Modifier {
+    Shift,
+    Ctrl,
+    Alt,
+    Meta,
+}
+
+impl Modifiers {
+    // Packed by the page as shift = 1, ctrl = 2, alt = 4 and meta = 8
+    pub fn from_bits(bits: u8) -> Self {
+        Modifiers {
+            shift: bits & 1 != 0,
+            ctrl: bits & 2 != 0,
+            alt: bits & 4 != 0,
+            meta: bits & 8 != 0,
+        }
+    }
+
+    pub fn has(&self, modifier: Modifier) -> bool {
+        match modifier {
+            Modifier::Shift => self.shift,
+            Modifier::Ctrl => self.ctrl,
+            Modifier::Alt => self.alt,
+            Modifier::Meta => self.meta,
+        }
+    }
 }
 
 pub struct PointerEventData {
     pub x: f64,
     pub y: f64,
     pub button: i8,
+    pub modifiers: Modifiers,
 }
 
 pub struct WheelEventData {
@@ -16,11 +89,14 @@ pub struct WheelEventData {
     pub dy: f64,
     pub px: f64,
     pub py: f64,
+    pub modifiers: Modifiers,
 }
 
+// Size in CSS pixels
 pub struct WindowResizeData {
     pub width: f64,
     pub height: f64,
+    pub pixel_ratio: f64,
 }
 
 pub enum GlobalEvent {
@@ -71,3 +147,26 @@ pub fn dispatch_global_event(event: GlobalEvent) -> () {
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_clock() {
+        let mut clock = FrameClock::new();
+        let ticks: Vec<TickEventData> = [10.0, 10.016, 15.0, 14.0]
+            .iter()
+            .map(|&time| clock.tick(time))
+            .collect();
+
+        assert_eq!(ticks.iter().map(|tick| tick.frame).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+        assert_eq!(ticks[0].delta, 0.0);
+        assert!((ticks[1].delta - 0.016).abs() < 1e-12);
+        // Back from a hidden tab
+        assert_eq!(ticks[2].delta, MAX_TICK_DELTA);
+        // Deltas are never negative
+        assert_eq!(ticks[3].delta, 0.0);
+        assert_eq!(ticks[3].time, 14.0);
+    }
+}
@@ -11,11 +11,12 @@ use interop::events::*;
 use interop::events::GlobalEvent::*;
 use sketches::Sketchbook;
 
+mod animation;
 mod common;
 mod interop;
 mod math;
 mod plotter;
-mod sketches;
+pub mod sketches;
 
 // Use `wee_alloc` as the global allocator.
 #[global_allocator]
@@ -23,43 +24,69 @@ static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
 thread_local! {
     pub static BOOK: RefCell<Sketchbook> = RefCell::new(Sketchbook::new());
+    static CLOCK: RefCell<FrameClock> = RefCell::new(FrameClock::new());
 }
 
 #[wasm_bindgen]
 pub fn on_load(args: Option<String>, canvas: web_sys::HtmlCanvasElement) -> () {
     if let Some(actual_args) = args {
+        // Frames are counted from the start of the sketch
+        CLOCK.with(|clock| *clock.borrow_mut() = FrameClock::new());
+
         BOOK.with(|x| {
             x.borrow_mut().load_sketch(actual_args, canvas);
         })
     }
 }
 
+// SVG of what the active sketch currently shows
+#[wasm_bindgen]
+pub fn export_svg() -> Option<String> {
+    BOOK.with(|x| x.borrow().export_svg())
+}
+
+// Plotter files for an A4 sheet
+#[wasm_bindgen]
+pub fn export_hpgl() -> Option<String> {
+    BOOK.with(|x| x.borrow().export_hpgl())
+}
+
+#[wasm_bindgen]
+pub fn export_gcode() -> Option<String> {
+    BOOK.with(|x| x.borrow().export_gcode())
+}
+
 #[wasm_bindgen]
 pub fn on_tick(time: f64) -> () {
-    dispatch_global_event(Tick(TickEventData { time }));
+    let data = CLOCK.with(|clock| clock.borrow_mut().tick(time));
+    dispatch_global_event(Tick(data));
 }
 
 #[wasm_bindgen]
-pub fn on_resize(width: f64, height: f64) -> () {
-    dispatch_global_event(WindowResize(WindowResizeData { width, height }));
+pub fn on_resize(width: f64, height: f64, pixel_ratio: f64) -> () {
+    dispatch_global_event(WindowResize(WindowResizeData { width, height, pixel_ratio }));
 }
 
 #[wasm_bindgen]
-pub fn on_pointer_down(button: i8, x: f64, y: f64) -> () {
-    dispatch_global_event(PointerDown(PointerEventData { button, x, y }));
+pub fn on_pointer_down(button: i8, x: f64, y: f64, modifiers: u8) -> () {
+    let modifiers = Modifiers::from_bits(modifiers);
+    dispatch_global_event(PointerDown(PointerEventData { button, x, y, modifiers }));
 }
 
 #[wasm_bindgen]
-pub fn on_pointer_move(button: i8, x: f64, y: f64) -> () {
-    dispatch_global_event(PointerMove(PointerEventData { button, x, y }));
+pub fn on_pointer_move(button: i8, x: f64, y: f64, modifiers: u8) -> () {
+    let modifiers = Modifiers::from_bits(modifiers);
+    dispatch_global_event(PointerMove(PointerEventData { button, x, y, modifiers }));
 }
 
 #[wasm_bindgen]
-pub fn on_pointer_up(button: i8, x: f64, y: f64) -> () {
-    dispatch_global_event(PointerUp(PointerEventData { button, x, y }));
+pub fn on_pointer_up(button: i8, x: f64, y: f64, modifiers: u8) -> () {
+    let modifiers = Modifiers::from_bits(modifiers);
+    dispatch_global_event(PointerUp(PointerEventData { button, x, y, modifiers }));
 }
 
 #[wasm_bindgen]
-pub fn on_wheel(dx: f64, dy: f64, px: f64, py: f64) -> () {
-    dispatch_global_event(Wheel(WheelEventData { dx, dy, px, py }));
+pub fn on_wheel(dx: f64, dy: f64, px: f64, py: f64, modifiers: u8) -> () {
+    let modifiers = Modifiers::from_bits(modifiers);
+    dispatch_global_event(Wheel(WheelEventData { dx, dy, px, py, modifiers }));
 }
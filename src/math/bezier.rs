@@ -0,0 +1,85 @@
+use super::vec3::Vec3;
+
+pub fn quadratic_bezier(p0: &Vec3, p1: &Vec3, p2: &Vec3, t: f64) -> Vec3 {
+    let u = 1.0 - t;
+
+    let a = u * u;
+    let b = 2.0 * u * t;
+    let c = t * t;
+
+    Vec3 {
+        x: a * p0.x + b * p1.x + c * p2.x,
+        y: a * p0.y + b * p1.y + c * p2.y,
+        z: a * p0.z + b * p1.z + c * p2.z,
+    }
+}
+
+pub fn cubic_bezier(p0: &Vec3, p1: &Vec3, p2: &Vec3, p3: &Vec3, t: f64) -> Vec3 {
+    let u = 1.0 - t;
+
+    let a = u * u * u;
+    let b = 3.0 * u * u * t;
+    let c = 3.0 * u * t * t;
+    let d = t * t * t;
+
+    Vec3 {
+        x: a * p0.x + b * p1.x + c * p2.x + d * p3.x,
+        y: a * p0.y + b * p1.y + c * p2.y + d * p3.y,
+        z: a * p0.z + b * p1.z + c * p2.z + d * p3.z,
+    }
+}
+
+// `segments + 1` evenly spaced (in t) points along the curve, ends included
+pub fn sample_quadratic_bezier(p0: &Vec3, p1: &Vec3, p2: &Vec3, segments: usize) -> Vec<Vec3> {
+    (0..=segments)
+        .map(|i| quadratic_bezier(p0, p1, p2, (i as f64) / (segments as f64)))
+        .collect()
+}
+
+pub fn sample_cubic_bezier(p0: &Vec3, p1: &Vec3, p2: &Vec3, p3: &Vec3, segments: usize) -> Vec<Vec3> {
+    (0..=segments)
+        .map(|i| cubic_bezier(p0, p1, p2, p3, (i as f64) / (segments as f64)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3;
+
+    #[test]
+    fn test_quadratic_bezier() {
+        let p0 = vec3!(0.0, 0.0, 1.0);
+        let p1 = vec3!(10.0, 20.0, 1.0);
+        let p2 = vec3!(20.0, 0.0, 1.0);
+
+        assert_eq!(quadratic_bezier(&p0, &p1, &p2, 0.0), p0);
+        assert_eq!(quadratic_bezier(&p0, &p1, &p2, 0.5), vec3!(10.0, 10.0, 1.0));
+        assert_eq!(quadratic_bezier(&p0, &p1, &p2, 1.0), p2);
+    }
+
+    #[test]
+    fn test_cubic_bezier() {
+        let p0 = vec3!(0.0, 0.0, 1.0);
+        let p1 = vec3!(0.0, 40.0, 1.0);
+        let p2 = vec3!(40.0, 40.0, 1.0);
+        let p3 = vec3!(40.0, 0.0, 1.0);
+
+        assert_eq!(cubic_bezier(&p0, &p1, &p2, &p3, 0.0), p0);
+        assert_eq!(cubic_bezier(&p0, &p1, &p2, &p3, 0.5), vec3!(20.0, 30.0, 1.0));
+        assert_eq!(cubic_bezier(&p0, &p1, &p2, &p3, 1.0), p3);
+    }
+
+    #[test]
+    fn test_sample_bezier() {
+        let p0 = vec3!(0.0, 0.0, 1.0);
+        let p1 = vec3!(5.0, 5.0, 1.0);
+        let p2 = vec3!(10.0, 10.0, 1.0);
+
+        let points = sample_quadratic_bezier(&p0, &p1, &p2, 4);
+
+        assert_eq!(points.len(), 5);
+        assert_eq!(points[1], vec3!(2.5, 2.5, 1.0));
+        assert_eq!(points[4], p2);
+    }
+}
@@ -0,0 +1,91 @@
+use super::vec3::Vec3;
+
+// Axis aligned rectangle, edges included
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Bounds {
+    pub min_x: f64,
+    pub min_y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+impl Bounds {
+    pub fn around(point: &Vec3, radius: f64) -> Self {
+        Bounds {
+            min_x: point.x - radius,
+            min_y: point.y - radius,
+            max_x: point.x + radius,
+            max_y: point.y + radius,
+        }
+    }
+
+    // None for no points, or when any of them is not finite
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Vec3>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let mut bounds = Bounds::around(points.next()?, 0.0);
+
+        for point in points {
+            bounds.min_x = bounds.min_x.min(point.x);
+            bounds.min_y = bounds.min_y.min(point.y);
+            bounds.max_x = bounds.max_x.max(point.x);
+            bounds.max_y = bounds.max_y.max(point.y);
+        }
+
+        Some(bounds).filter(Bounds::is_finite)
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.min_x.is_finite() && self.min_y.is_finite() && self.max_x.is_finite() && self.max_y.is_finite()
+    }
+
+    pub fn width(&self) -> f64 {
+        self.max_x - self.min_x
+    }
+
+    pub fn height(&self) -> f64 {
+        self.max_y - self.min_y
+    }
+
+    pub fn expand(&self, margin: f64) -> Self {
+        Bounds {
+            min_x: self.min_x - margin,
+            min_y: self.min_y - margin,
+            max_x: self.max_x + margin,
+            max_y: self.max_y + margin,
+        }
+    }
+
+    pub fn contains(&self, other: &Bounds) -> bool {
+        self.min_x <= other.min_x
+            && self.min_y <= other.min_y
+            && self.max_x >= other.max_x
+            && self.max_y >= other.max_y
+    }
+
+    pub fn intersects(&self, other: &Bounds) -> bool {
+        self.min_x <= other.max_x
+            && other.min_x <= self.max_x
+            && self.min_y <= other.max_y
+            && other.min_y <= self.max_y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3;
+
+    #[test]
+    fn test_bounds() {
+        let points = [vec3!(1.0, 5.0, 1.0), vec3!(-2.0, 3.0, 1.0), vec3!(4.0, -1.0, 1.0)];
+        let bounds = Bounds::from_points(&points).unwrap();
+
+        assert_eq!(bounds, Bounds { min_x: -2.0, min_y: -1.0, max_x: 4.0, max_y: 5.0 });
+        assert!(bounds.contains(&Bounds::around(&vec3!(0.0, 0.0, 1.0), 1.0)));
+        assert!(bounds.intersects(&Bounds::around(&vec3!(5.0, 6.0, 1.0), 1.0)));
+        assert!(!bounds.intersects(&Bounds::around(&vec3!(6.0, 6.0, 1.0), 1.0)));
+
+        assert_eq!(Bounds::from_points(&[]), None);
+        assert_eq!(Bounds::from_points(&[vec3!(f64::INFINITY, 0.0, 1.0)]), None);
+    }
+}
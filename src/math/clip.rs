@@ -0,0 +1,164 @@
+use super::vec3::Vec3;
+
+// Cyrus-Beck clipping of the parametric line `origin + t * direction`
+// against a convex polygon with either winding. Returns the part of
+// [t_min, t_max] that lies inside the polygon, if any.
+pub fn clip_line(
+    origin: &Vec3,
+    direction: &Vec3,
+    t_min: f64,
+    t_max: f64,
+    polygon: &[Vec3],
+) -> Option<(f64, f64)> {
+    if direction.x == 0.0 && direction.y == 0.0 {
+        return None;
+    }
+
+    let orientation = signed_area(polygon).signum();
+
+    if orientation == 0.0 {
+        return None;
+    }
+
+    let mut from = t_min;
+    let mut to = t_max;
+
+    for i in 0..polygon.len() {
+        let a = &polygon[i];
+        let b = &polygon[(i + 1) % polygon.len()];
+
+        // Inward facing edge normal
+        let nx = -(b.y - a.y) * orientation;
+        let ny = (b.x - a.x) * orientation;
+
+        let distance = nx * (origin.x - a.x) + ny * (origin.y - a.y);
+        let speed = nx * direction.x + ny * direction.y;
+
+        if speed == 0.0 {
+            if distance < 0.0 {
+                return None;
+            }
+        } else {
+            let t = -distance / speed;
+
+            if speed > 0.0 {
+                from = f64::max(from, t);
+            } else {
+                to = f64::min(to, t);
+            }
+        }
+
+        if from > to {
+            return None;
+        }
+    }
+
+    Some((from, to))
+}
+
+pub fn signed_area(polygon: &[Vec3]) -> f64 {
+    let mut area = 0.0;
+
+    for i in 0..polygon.len() {
+        let a = &polygon[i];
+        let b = &polygon[(i + 1) % polygon.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+
+    area / 2.0
+}
+
+// How many times the polygon winds around the point, counterclockwise
+// in a y-up frame being positive
+pub fn winding_number(point: &Vec3, polygon: &[Vec3]) -> i32 {
+    let mut winding = 0;
+
+    for i in 0..polygon.len() {
+        let a = &polygon[i];
+        let b = &polygon[(i + 1) % polygon.len()];
+        let side = (b.x - a.x) * (point.y - a.y) - (point.x - a.x) * (b.y - a.y);
+
+        if a.y <= point.y && b.y > point.y && side > 0.0 {
+            winding += 1;
+        } else if b.y <= point.y && a.y > point.y && side < 0.0 {
+            winding -= 1;
+        }
+    }
+
+    winding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3;
+
+    fn square() -> Vec<Vec3> {
+        vec![
+            vec3!(0.0, 0.0, 1.0),
+            vec3!(10.0, 0.0, 1.0),
+            vec3!(10.0, 10.0, 1.0),
+            vec3!(0.0, 10.0, 1.0),
+        ]
+    }
+
+    #[test]
+    fn test_clip_infinite_line() {
+        let clipped = clip_line(
+            &vec3!(5.0, 5.0, 1.0),
+            &vec3!(1.0, 0.0, 0.0),
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            &square(),
+        );
+
+        assert_eq!(clipped, Some((-5.0, 5.0)));
+    }
+
+    #[test]
+    fn test_clip_ray_with_either_winding() {
+        let mut polygon = square();
+        let origin = vec3!(-10.0, 5.0, 1.0);
+        let direction = vec3!(2.0, 0.0, 0.0);
+
+        let clipped = clip_line(&origin, &direction, 0.0, f64::INFINITY, &polygon);
+        assert_eq!(clipped, Some((5.0, 10.0)));
+
+        polygon.reverse();
+        let clipped = clip_line(&origin, &direction, 0.0, f64::INFINITY, &polygon);
+        assert_eq!(clipped, Some((5.0, 10.0)));
+    }
+
+    #[test]
+    fn test_clip_outside() {
+        let backwards = clip_line(
+            &vec3!(20.0, 5.0, 1.0),
+            &vec3!(1.0, 0.0, 0.0),
+            0.0,
+            f64::INFINITY,
+            &square(),
+        );
+
+        let parallel = clip_line(
+            &vec3!(0.0, 15.0, 1.0),
+            &vec3!(1.0, 0.0, 0.0),
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            &square(),
+        );
+
+        assert_eq!(backwards, None);
+        assert_eq!(parallel, None);
+    }
+
+    #[test]
+    fn test_winding_number() {
+        let mut polygon = square();
+
+        assert_eq!(winding_number(&vec3!(5.0, 5.0, 1.0), &polygon), 1);
+        assert_eq!(winding_number(&vec3!(15.0, 5.0, 1.0), &polygon), 0);
+
+        polygon.reverse();
+        assert_eq!(winding_number(&vec3!(5.0, 5.0, 1.0), &polygon), -1);
+    }
+}
@@ -0,0 +1,60 @@
+use super::vec3::Vec3;
+
+// Distance from a point to the part of the parametric line
+// `origin + t * direction` with `t` in [t_min, t_max]
+pub fn distance_to_line(point: &Vec3, origin: &Vec3, direction: &Vec3, t_min: f64, t_max: f64) -> f64 {
+    let dx = point.x - origin.x;
+    let dy = point.y - origin.y;
+    let length_2 = direction.x * direction.x + direction.y * direction.y;
+
+    let t = if length_2 > 0.0 {
+        ((dx * direction.x + dy * direction.y) / length_2).clamp(t_min, t_max)
+    } else {
+        0.0
+    };
+
+    f64::hypot(dx - direction.x * t, dy - direction.y * t)
+}
+
+// Distance from a point to the closest segment of a polyline
+pub fn distance_to_polyline(point: &Vec3, points: &[Vec3], closed: bool) -> f64 {
+    let segments = match points.len() {
+        0 => return f64::INFINITY,
+        1 => return f64::hypot(point.x - points[0].x, point.y - points[0].y),
+        count if closed && count > 2 => count,
+        count => count - 1,
+    };
+
+    (0..segments)
+        .map(|i| {
+            let a = &points[i];
+            let b = &points[(i + 1) % points.len()];
+            distance_to_line(point, a, &(b - a), 0.0, 1.0)
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3;
+
+    #[test]
+    fn test_distance_to_line() {
+        let origin = vec3!(0.0, 0.0, 1.0);
+        let direction = vec3!(2.0, 0.0, 0.0);
+
+        assert_eq!(distance_to_line(&vec3!(1.0, 3.0, 1.0), &origin, &direction, 0.0, 1.0), 3.0);
+        assert_eq!(distance_to_line(&vec3!(5.0, 4.0, 1.0), &origin, &direction, 0.0, 1.0), 5.0);
+        assert_eq!(distance_to_line(&vec3!(-3.0, 4.0, 1.0), &origin, &direction, 0.0, f64::INFINITY), 5.0);
+        assert_eq!(distance_to_line(&vec3!(-3.0, 4.0, 1.0), &origin, &direction, f64::NEG_INFINITY, f64::INFINITY), 4.0);
+    }
+
+    #[test]
+    fn test_distance_to_polyline() {
+        let points = vec![vec3!(0.0, 0.0, 1.0), vec3!(10.0, 0.0, 1.0), vec3!(10.0, 10.0, 1.0)];
+
+        assert_eq!(distance_to_polyline(&vec3!(2.0, 8.0, 1.0), &points, false), 8.0);
+        assert!(distance_to_polyline(&vec3!(2.0, 8.0, 1.0), &points, true) < 8.0);
+    }
+}
@@ -0,0 +1,181 @@
+use std::f64::consts::PI;
+
+use super::mat3::Mat3;
+use super::vec3::Vec3;
+
+// Elliptical arc in the same terms as CanvasRenderingContext2d::ellipse:
+// points are `center + R(rotation) * (rx * cos(t), ry * sin(t))` for `t`
+// going from `start` to `end`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct EllipticalArc {
+    pub center: Vec3,
+    pub rx: f64,
+    pub ry: f64,
+    pub rotation: f64,
+    pub start: f64,
+    pub end: f64,
+    pub anticlockwise: bool,
+}
+
+impl EllipticalArc {
+    pub fn full(center: Vec3, rx: f64, ry: f64, rotation: f64) -> Self {
+        EllipticalArc {
+            center,
+            rx,
+            ry,
+            rotation,
+            start: 0.0,
+            end: PI * 2.0,
+            anticlockwise: false,
+        }
+    }
+
+    pub fn point_at(&self, t: f64) -> Vec3 {
+        let (sin, cos) = f64::sin_cos(self.rotation);
+        let x = self.rx * f64::cos(t);
+        let y = self.ry * f64::sin(t);
+
+        Vec3 {
+            x: self.center.x + cos * x - sin * y,
+            y: self.center.y + sin * x + cos * y,
+            z: 1.0,
+        }
+    }
+
+    // Signed angle covered by the arc, following the canvas ellipse() rules
+    pub fn sweep(&self) -> f64 {
+        let full = PI * 2.0;
+
+        if !self.anticlockwise {
+            let sweep = self.end - self.start;
+            if sweep >= full { full } else { sweep.rem_euclid(full) }
+        } else {
+            let sweep = self.start - self.end;
+            if sweep >= full { -full } else { -sweep.rem_euclid(full) }
+        }
+    }
+
+    // Affine image of the arc, which is an elliptical arc again.
+    pub fn transform(&self, m: &Mat3) -> Self {
+        let (sin, cos) = f64::sin_cos(self.rotation);
+
+        // Linear part of the transform applied to R(rotation) * diag(rx, ry)
+        let a = (m.m11 * cos + m.m12 * sin) * self.rx;
+        let b = (m.m12 * cos - m.m11 * sin) * self.ry;
+        let c = (m.m21 * cos + m.m22 * sin) * self.rx;
+        let d = (m.m22 * cos - m.m21 * sin) * self.ry;
+
+        // Closed form 2x2 SVD: [a b; c d] = R(phi) * diag(s1, s2) * R(theta)
+        let e = (a + d) / 2.0;
+        let f = (a - d) / 2.0;
+        let g = (c + b) / 2.0;
+        let h = (c - b) / 2.0;
+
+        let q = f64::hypot(e, h);
+        let r = f64::hypot(f, g);
+        let a1 = f64::atan2(g, f);
+        let a2 = f64::atan2(h, e);
+
+        let s1 = q + r;
+        let s2 = q - r;
+        let theta = (a2 - a1) / 2.0;
+        let phi = (a2 + a1) / 2.0;
+
+        let center = m * &self.center;
+
+        if s2 >= 0.0 {
+            EllipticalArc {
+                center,
+                rx: s1,
+                ry: s2,
+                rotation: phi,
+                start: self.start + theta,
+                end: self.end + theta,
+                anticlockwise: self.anticlockwise,
+            }
+        } else {
+            // The transform mirrors the arc, so it is traced the other way
+            EllipticalArc {
+                center,
+                rx: s1,
+                ry: -s2,
+                rotation: phi,
+                start: -(self.start + theta),
+                end: -(self.end + theta),
+                anticlockwise: !self.anticlockwise,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3;
+
+    fn assert_same_points(expected: &EllipticalArc, actual: &EllipticalArc, m: &Mat3) {
+        for i in 0..=8 {
+            let k = (i as f64) / 8.0;
+            let t = expected.start + (expected.end - expected.start) * k;
+            let u = actual.start + (actual.end - actual.start) * k;
+
+            let a = m * &expected.point_at(t);
+            let b = actual.point_at(u);
+
+            assert!(f64::abs(a.x - b.x) < 1e-9 && f64::abs(a.y - b.y) < 1e-9, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_scaled_circle_becomes_ellipse() {
+        let m = Mat3::identity().scale(&vec3!(2.0, 3.0, 1.0));
+        let circle = EllipticalArc::full(vec3!(1.0, 1.0, 1.0), 10.0, 10.0, 0.0);
+        let ellipse = circle.transform(&m);
+
+        assert_eq!(ellipse.center, vec3!(2.0, 3.0, 1.0));
+        assert!(f64::abs(f64::max(ellipse.rx, ellipse.ry) - 30.0) < 1e-9);
+        assert!(f64::abs(f64::min(ellipse.rx, ellipse.ry) - 20.0) < 1e-9);
+    }
+
+    #[test]
+    fn test_transform_arc() {
+        let m = Mat3::identity()
+            .translate(&vec3!(120.0, 130.0, 1.0))
+            .rotate(PI / 2.5)
+            .scale(&vec3!(1.5, 0.5, 1.0));
+
+        let arc = EllipticalArc {
+            center: vec3!(10.0, -5.0, 1.0),
+            rx: 7.0,
+            ry: 3.0,
+            rotation: 0.3,
+            start: 0.2,
+            end: 2.5,
+            anticlockwise: false,
+        };
+
+        assert_same_points(&arc, &arc.transform(&m), &m);
+    }
+
+    #[test]
+    fn test_transform_mirrored_arc() {
+        let m = Mat3::identity()
+            .rotate(0.7)
+            .scale(&vec3!(-2.0, 1.0, 1.0));
+
+        let arc = EllipticalArc {
+            center: vec3!(3.0, 4.0, 1.0),
+            rx: 5.0,
+            ry: 5.0,
+            rotation: 0.0,
+            start: 0.0,
+            end: PI / 2.0,
+            anticlockwise: false,
+        };
+
+        let mirrored = arc.transform(&m);
+
+        assert!(mirrored.anticlockwise);
+        assert_same_points(&arc, &mirrored, &m);
+    }
+}
@@ -1,7 +1,7 @@
 use std::ops;
 use super::vec3::Vec3;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct Mat3 {
     pub m11: f64, pub m12: f64, pub m13: f64,
     pub m21: f64, pub m22: f64, pub m23: f64,
@@ -85,6 +85,12 @@ impl Mat3 {
         }
     }
 
+    // How much the 2D part of the transform scales lengths, on average.
+    // Exact for transforms without non-uniform scaling or shear.
+    pub fn linear_scale(&self) -> f64 {
+        f64::sqrt(f64::abs(self.m11 * self.m22 - self.m12 * self.m21))
+    }
+
     pub fn inverse(&mut self) -> bool {
         // Code from: https://docs.rs/nalgebra/0.21.1/src/nalgebra/linalg/inverse.rs.html#31-119
 
@@ -178,6 +184,16 @@ mod tests {
         assert_eq!(transformed, Vec3 { x: 15.0, y: 15.0, z: 1.0 });
     }
 
+    #[test]
+    fn test_linear_scale() {
+        let transform = Mat3::identity()
+            .translate(&Vec3 { x: 15.0, y: 15.0, z: 1.0 })
+            .rotate(0.5)
+            .scale(&Vec3 { x: 3.0, y: 3.0, z: 1.0 });
+
+        assert!(f64::abs(transform.linear_scale() - 3.0) < 0.00001);
+    }
+
     #[test]
     fn test_inverse() {
         let transform = Mat3::identity()
@@ -1,3 +1,8 @@
 pub mod vec3;
 pub mod mat3;
 pub mod utils;
+pub mod clip;
+pub mod ellipse;
+pub mod bezier;
+pub mod distance;
+pub mod bounds;
@@ -3,7 +3,7 @@ use std::ops;
 use std::cmp::{Eq};
 use super::utils::align;
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub struct Vec3 {
     pub x: f64,
     pub y: f64,
@@ -19,13 +19,6 @@ impl Vec3 {
         f64::sqrt(self.x * self.x + self.y * self.y + self.z * self.z)
     }
 
-    pub fn as_rgb_string(&self) -> String {
-        let r: u8 = (self.x * 255.0) as u8;
-        let g: u8 = (self.y * 255.0) as u8;
-        let b: u8 = (self.z * 255.0) as u8;
-        (&format!("rgb({}, {}, {})", r, g, b)).into()
-    }
-
     pub fn align(&self, step: f64) -> Self {
         Vec3 {
             x: align(self.x, step),
@@ -46,7 +39,7 @@ impl<'a> ops::Add<&'a Vec3> for &'a Vec3 {
         Vec3 {
             x: self.x + rhs.x,
             y: self.y + rhs.y,
-            z: self.y + rhs.z,
+            z: self.z + rhs.z,
         }
     }
 }
@@ -59,7 +52,7 @@ impl<'a> ops::Sub<&'a Vec3> for &'a Vec3 {
         Vec3 {
             x: self.x - rhs.x,
             y: self.y - rhs.y,
-            z: self.y - rhs.z,
+            z: self.z - rhs.z,
         }
     }
 }
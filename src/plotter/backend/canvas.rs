@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::CanvasRenderingContext2d;
+use web_sys::CanvasWindingRule;
+use web_sys::HtmlCanvasElement;
+
+use crate::common::colors::Color;
+use crate::math::mat3::Mat3;
+use crate::math::vec3::Vec3;
+use crate::plotter::path::{Path, PathCommand};
+use crate::plotter::style::*;
+use crate::plotter::{Text, TextAlign, TextBaseline};
+use crate::vec3;
+
+use super::Backend;
+
+pub struct CanvasBackend {
+    canvas: HtmlCanvasElement,
+    context: CanvasRenderingContext2d,
+    css_colors: HashMap<[u8; 4], JsValue>,
+    // Device pixels per CSS pixel. Everything is drawn in CSS pixels, the
+    // backing store is scaled up by this much.
+    pixel_ratio: f64,
+}
+
+impl CanvasBackend {
+    pub fn new(canvas: HtmlCanvasElement) -> Self {
+        let context = canvas
+            .get_context("2d")
+            .unwrap()
+            .unwrap()
+            .dyn_into::<web_sys::CanvasRenderingContext2d>()
+            .unwrap();
+
+        CanvasBackend {
+            canvas,
+            context,
+            css_colors: HashMap::new(),
+            pixel_ratio: 1.0,
+        }
+    }
+
+    // CSS strings are only built once per distinct color
+    fn css_color(&mut self, color: &Color) -> JsValue {
+        self.css_colors
+            .entry(color.to_rgba8())
+            .or_insert_with(|| color.as_css_string().into())
+            .clone()
+    }
+
+    fn trace(&self, path: &Path) {
+        let ctx: &CanvasRenderingContext2d = &self.context;
+
+        ctx.begin_path();
+
+        for command in &path.commands {
+            match *command {
+                PathCommand::MoveTo(x, y) => ctx.move_to(x, y),
+                PathCommand::LineTo(x, y) => ctx.line_to(x, y),
+                PathCommand::QuadraticCurveTo(cpx, cpy, x, y) => {
+                    ctx.quadratic_curve_to(cpx, cpy, x, y)
+                }
+                PathCommand::BezierCurveTo(cp1x, cp1y, cp2x, cp2y, x, y) => {
+                    ctx.bezier_curve_to(cp1x, cp1y, cp2x, cp2y, x, y)
+                }
+                PathCommand::Ellipse(arc) => {
+                    ctx.ellipse_with_anticlockwise(
+                        arc.center.x,
+                        arc.center.y,
+                        arc.rx,
+                        arc.ry,
+                        arc.rotation,
+                        arc.start,
+                        arc.end,
+                        arc.anticlockwise,
+                    ).unwrap();
+                }
+                PathCommand::ClosePath => ctx.close_path(),
+            }
+        }
+    }
+}
+
+impl Backend for CanvasBackend {
+    fn size(&self) -> (f64, f64) {
+        (
+            f64::from(self.canvas.width()) / self.pixel_ratio,
+            f64::from(self.canvas.height()) / self.pixel_ratio,
+        )
+    }
+
+    fn resize(&mut self, width: f64, height: f64, pixel_ratio: f64) {
+        self.pixel_ratio = pixel_ratio;
+
+        // Resizing the canvas resets the context, transform included
+        self.canvas.set_width((width * pixel_ratio).round() as u32);
+        self.canvas.set_height((height * pixel_ratio).round() as u32);
+        self.context.set_transform(pixel_ratio, 0.0, 0.0, pixel_ratio, 0.0, 0.0).unwrap();
+    }
+
+    fn clear(&mut self, color: &Color) {
+        let (width, height) = self.size();
+        let css_color = self.css_color(color);
+
+        self.context.set_fill_style(&css_color);
+        self.context.fill_rect(0.0, 0.0, width, height);
+    }
+
+    fn set_stroke_style(&mut self, color: &Color, style: &StrokeStyle) {
+        let css_color = self.css_color(color);
+        let ctx: &CanvasRenderingContext2d = &self.context;
+
+        let dash: js_sys::Array = style.dash
+            .iter()
+            .map(|length| JsValue::from_f64(*length))
+            .collect();
+
+        ctx.set_stroke_style(&css_color);
+        ctx.set_line_width(style.width.value());
+        ctx.set_line_dash(&dash).unwrap();
+        ctx.set_line_dash_offset(style.dash_offset);
+
+        ctx.set_line_cap(match style.cap {
+            LineCap::Butt => "butt",
+            LineCap::Round => "round",
+            LineCap::Square => "square",
+        });
+
+        ctx.set_line_join(match style.join {
+            LineJoin::Miter => "miter",
+            LineJoin::Round => "round",
+            LineJoin::Bevel => "bevel",
+        });
+
+        ctx.set_miter_limit(style.miter_limit);
+    }
+
+    fn set_fill_style(&mut self, color: &Color) {
+        let css_color = self.css_color(color);
+        self.context.set_fill_style(&css_color);
+    }
+
+    fn stroke(&mut self, path: &Path) {
+        self.trace(path);
+        self.context.stroke();
+    }
+
+    fn fill(&mut self, path: &Path, rule: FillRule) {
+        self.trace(path);
+
+        self.context.fill_with_canvas_winding_rule(match rule {
+            FillRule::NonZero => CanvasWindingRule::Nonzero,
+            FillRule::EvenOdd => CanvasWindingRule::Evenodd,
+        });
+    }
+
+    fn fill_text(&mut self, text: &Text, placement: &Mat3) {
+        let ctx: &CanvasRenderingContext2d = &self.context;
+        let ratio = self.pixel_ratio;
+        let m = &Mat3::identity().scale(&vec3!(ratio, ratio, 1.0)) * placement;
+
+        ctx.save();
+        ctx.set_transform(m.m11, m.m21, m.m12, m.m22, m.m13, m.m23).unwrap();
+        ctx.set_font(&format!("{}px {}", text.font.size, text.font.family));
+
+        ctx.set_text_align(match text.align {
+            TextAlign::Left => "left",
+            TextAlign::Center => "center",
+            TextAlign::Right => "right",
+        });
+
+        ctx.set_text_baseline(match text.baseline {
+            TextBaseline::Top => "top",
+            TextBaseline::Middle => "middle",
+            TextBaseline::Alphabetic => "alphabetic",
+            TextBaseline::Bottom => "bottom",
+        });
+
+        ctx.fill_text(&text.content, 0.0, 0.0).unwrap();
+        ctx.restore();
+    }
+
+    // The opacity applies to every shape on its own, so overlapping shapes
+    // of a faded layer show through each other
+    fn begin_layer(&mut self, _name: &str, opacity: f64) {
+        self.context.save();
+        self.context.set_global_alpha(opacity);
+    }
+
+    fn end_layer(&mut self) {
+        self.context.restore();
+    }
+}
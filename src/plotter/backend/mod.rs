@@ -0,0 +1,39 @@
+use crate::common::colors::Color;
+use crate::math::mat3::Mat3;
+use crate::math::vec3::Vec3;
+
+use super::path::Path;
+use super::style::{FillRule, StrokeStyle};
+use super::Text;
+
+pub mod canvas;
+mod font;
+pub mod pen;
+pub mod raster;
+pub mod recording;
+pub mod svg;
+
+// Everything the Plotter needs to draw a frame. Coordinates are already
+// projected to the canvas, and stroke styles are always in screen units.
+pub trait Backend {
+    fn size(&self) -> (f64, f64);
+    // Size in CSS pixels, drawn with `pixel_ratio` device pixels to each of
+    // them. Backends of a fixed size ignore it.
+    fn resize(&mut self, _width: f64, _height: f64, _pixel_ratio: f64) {}
+    fn clear(&mut self, color: &Color);
+    fn set_stroke_style(&mut self, color: &Color, style: &StrokeStyle);
+    fn set_fill_style(&mut self, color: &Color);
+    fn stroke(&mut self, path: &Path);
+    fn fill(&mut self, path: &Path, rule: FillRule);
+    // Points are small discs traced by `path` around `center`
+    fn fill_point(&mut self, _center: &Vec3, path: &Path) {
+        self.fill(path, FillRule::NonZero);
+    }
+    // `placement` maps the text space, with the anchor at the origin, onto the canvas
+    fn fill_text(&mut self, text: &Text, placement: &Mat3);
+    // Everything drawn until end_layer belongs to the named layer. Its
+    // opacity fades every shape on its own rather than the layer as a
+    // whole, so overlapping shapes show through each other. Layers don't nest.
+    fn begin_layer(&mut self, _name: &str, _opacity: f64) {}
+    fn end_layer(&mut self) {}
+}
@@ -0,0 +1,193 @@
+use crate::common::colors::Color;
+use crate::math::clip::clip_line;
+use crate::math::mat3::Mat3;
+use crate::math::vec3::Vec3;
+use crate::plotter::path::Path;
+use crate::plotter::pen::{PenPath, PointMode};
+use crate::plotter::style::{FillRule, StrokeStyle};
+use crate::plotter::Text;
+use crate::vec3;
+
+use super::Backend;
+
+// Collects strokes as polylines for pen plotters, in canvas pixels. A pen
+// can't fill areas or write text, so only outlines and points are kept,
+// cut to the canvas so that nothing is plotted off the page.
+pub struct PenBackend {
+    size: (f64, f64),
+    tolerance: f64,
+    points: PointMode,
+    // Gives every layer a pen of its own instead of every color
+    layer_pens: bool,
+    layer: Option<String>,
+    stroke_color: Color,
+    fill_color: Color,
+    pub paths: Vec<PenPath>,
+}
+
+impl PenBackend {
+    pub fn new(width: f64, height: f64, tolerance: f64, points: PointMode, layer_pens: bool) -> Self {
+        PenBackend {
+            size: (width, height),
+            tolerance,
+            points,
+            layer_pens,
+            layer: None,
+            stroke_color: Color::rgb(0.0, 0.0, 0.0),
+            fill_color: Color::rgb(0.0, 0.0, 0.0),
+            paths: Vec::new(),
+        }
+    }
+
+    fn bounds(&self) -> [Vec3; 4] {
+        let (width, height) = self.size;
+
+        [
+            vec3!(0.0, 0.0, 1.0),
+            vec3!(width, 0.0, 1.0),
+            vec3!(width, height, 1.0),
+            vec3!(0.0, height, 1.0),
+        ]
+    }
+
+    fn push_clipped(&mut self, points: &[Vec3]) {
+        let bounds = self.bounds();
+        let mut current: Vec<Vec3> = Vec::new();
+
+        for pair in points.windows(2) {
+            let direction = &pair[1] - &pair[0];
+
+            match clip_line(&pair[0], &direction, 0.0, 1.0, &bounds) {
+                Some((from, to)) => {
+                    let start = &pair[0] + &(&direction * from);
+                    let end = &pair[0] + &(&direction * to);
+
+                    // Leaving and re-entering the canvas starts a new stroke
+                    if from > 0.0 || current.is_empty() {
+                        self.finish(&mut current);
+                        current.push(start);
+                    }
+
+                    current.push(end);
+
+                    if to < 1.0 {
+                        self.finish(&mut current);
+                    }
+                }
+                None => self.finish(&mut current),
+            }
+        }
+
+        self.finish(&mut current);
+    }
+
+    fn finish(&mut self, points: &mut Vec<Vec3>) {
+        if points.len() > 1 {
+            self.paths.push(PenPath {
+                color: self.stroke_color,
+                layer: self.layer.clone(),
+                points: std::mem::take(points),
+            });
+        }
+
+        points.clear();
+    }
+}
+
+impl Backend for PenBackend {
+    fn size(&self) -> (f64, f64) {
+        self.size
+    }
+
+    fn clear(&mut self, _color: &Color) {
+        self.paths.clear();
+    }
+
+    fn set_stroke_style(&mut self, color: &Color, _style: &StrokeStyle) {
+        self.stroke_color = *color;
+    }
+
+    fn set_fill_style(&mut self, color: &Color) {
+        self.fill_color = *color;
+    }
+
+    fn stroke(&mut self, path: &Path) {
+        for subpath in path.flatten(self.tolerance) {
+            let mut points = subpath.points;
+
+            if subpath.closed {
+                points.push(points[0]);
+            }
+
+            points.dedup();
+            self.push_clipped(&points);
+        }
+    }
+
+    fn fill(&mut self, _path: &Path, _rule: FillRule) {}
+
+    fn fill_point(&mut self, center: &Vec3, _path: &Path) {
+        let (width, height) = self.size;
+        let inside = (0.0..=width).contains(&center.x) && (0.0..=height).contains(&center.y);
+
+        if self.points == PointMode::Dot && inside {
+            self.paths.push(PenPath {
+                color: self.fill_color,
+                layer: self.layer.clone(),
+                points: vec![vec3!(center.x, center.y, 1.0)],
+            });
+        }
+    }
+
+    fn fill_text(&mut self, _text: &Text, _placement: &Mat3) {}
+
+    fn begin_layer(&mut self, name: &str, _opacity: f64) {
+        if self.layer_pens {
+            self.layer = Some(name.to_string());
+        }
+    }
+
+    fn end_layer(&mut self) {
+        self.layer = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clip_to_canvas() {
+        let mut pen = PenBackend::new(10.0, 10.0, 0.1, PointMode::Dot, false);
+        let mut path = Path::new();
+
+        path.move_to(5.0, 5.0);
+        path.line_to(15.0, 5.0);
+        path.line_to(15.0, 8.0);
+        path.line_to(5.0, 8.0);
+        path.line_to(5.0, 9.0);
+
+        pen.stroke(&path);
+
+        let paths: Vec<Vec<Vec3>> = pen.paths.into_iter().map(|path| path.points).collect();
+
+        assert_eq!(paths, vec![
+            vec![vec3!(5.0, 5.0, 1.0), vec3!(10.0, 5.0, 1.0)],
+            vec![vec3!(10.0, 8.0, 1.0), vec3!(5.0, 8.0, 1.0), vec3!(5.0, 9.0, 1.0)],
+        ]);
+    }
+
+    #[test]
+    fn test_points() {
+        let mut dots = PenBackend::new(10.0, 10.0, 0.1, PointMode::Dot, false);
+        dots.fill_point(&vec3!(1.0, 2.0, 1.0), &Path::new());
+        dots.fill_point(&vec3!(11.0, 2.0, 1.0), &Path::new());
+
+        let mut skipped = PenBackend::new(10.0, 10.0, 0.1, PointMode::Skip, false);
+        skipped.fill_point(&vec3!(1.0, 2.0, 1.0), &Path::new());
+
+        assert_eq!(dots.paths.len(), 1);
+        assert!(dots.paths[0].is_dot());
+        assert!(skipped.paths.is_empty());
+    }
+}
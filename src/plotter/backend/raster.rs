@@ -0,0 +1,714 @@
+use std::f64::consts::PI;
+
+use crate::common::colors::Color;
+use crate::common::png::encode_png;
+use crate::math::clip::signed_area;
+use crate::math::mat3::Mat3;
+use crate::math::vec3::Vec3;
+use crate::plotter::path::Path;
+use crate::plotter::style::*;
+use crate::plotter::{Text, TextAlign, TextBaseline};
+use crate::vec3;
+
+use super::Backend;
+use super::font;
+
+// Largest distance between a curve and its flattened version, in pixels
+const TOLERANCE: f64 = 0.1;
+
+// Vertical anti-aliasing samples per pixel row. Horizontal coverage is exact.
+const SUBSCANLINES: usize = 5;
+
+// Software renderer for when there is no browser around, e.g. for
+// thumbnails and regression images. Text is drawn with a built in bitmap
+// font whatever the font family, so it only roughly matches the canvas.
+pub struct RasterBackend {
+    width: usize,
+    height: usize,
+    // Premultiplied RGBA
+    pixels: Vec<[f64; 4]>,
+    stroke_color: Color,
+    stroke_style: StrokeStyle,
+    fill_color: Color,
+    // Of the current layer
+    opacity: f64,
+}
+
+impl RasterBackend {
+    pub fn new(width: usize, height: usize) -> Self {
+        RasterBackend {
+            width,
+            height,
+            pixels: vec![[0.0; 4]; width * height],
+            stroke_color: Color::rgb(0.0, 0.0, 0.0),
+            stroke_style: StrokeStyle::default(),
+            fill_color: Color::rgb(0.0, 0.0, 0.0),
+            opacity: 1.0,
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let [r, g, b, a] = self.pixels[y * self.width + x];
+
+        if a == 0.0 {
+            return [0, 0, 0, 0];
+        }
+
+        Color::rgba(r / a, g / a, b / a, a).to_rgba8()
+    }
+
+    // Straight alpha RGBA rows, top to bottom
+    pub fn pixels(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.width * self.height * 4);
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                bytes.extend_from_slice(&self.pixel(x, y));
+            }
+        }
+
+        bytes
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        encode_png(self.width as u32, self.height as u32, &self.pixels())
+    }
+
+    fn blend(&mut self, index: usize, color: &Color, coverage: f64) {
+        let alpha = color.a * coverage.min(1.0);
+        let pixel = &mut self.pixels[index];
+        let keep = 1.0 - alpha;
+
+        pixel[0] = color.r * alpha + pixel[0] * keep;
+        pixel[1] = color.g * alpha + pixel[1] * keep;
+        pixel[2] = color.b * alpha + pixel[2] * keep;
+        pixel[3] = alpha + pixel[3] * keep;
+    }
+
+    // Scanline rasterizer: every pixel row is sampled along a few horizontal
+    // lines, and the spans inside the polygons add up to the pixel coverage.
+    fn fill_polygons(&mut self, polygons: &[Vec<Vec3>], rule: FillRule, color: &Color) {
+        let color = &color.with_alpha(color.a * self.opacity);
+        let mut edges: Vec<Edge> = Vec::new();
+
+        for polygon in polygons {
+            for i in 0..polygon.len() {
+                let a = &polygon[i];
+                let b = &polygon[(i + 1) % polygon.len()];
+
+                if a.y != b.y && a.x.is_finite() && b.x.is_finite() {
+                    edges.push(Edge::new(a, b));
+                }
+            }
+        }
+
+        if edges.is_empty() || self.width == 0 {
+            return;
+        }
+
+        edges.sort_by(|a, b| a.top.total_cmp(&b.top));
+
+        let top = edges[0].top.max(0.0).floor() as usize;
+        let bottom = edges
+            .iter()
+            .fold(f64::MIN, |bottom, edge| bottom.max(edge.bottom))
+            .min(self.height as f64)
+            .ceil() as usize;
+
+        let mut next_edge = 0;
+        let mut active: Vec<&Edge> = Vec::new();
+        let mut crossings: Vec<(f64, i32)> = Vec::new();
+        let mut coverage = vec![0.0; self.width + 1];
+
+        for row in top..bottom {
+            coverage.iter_mut().for_each(|value| *value = 0.0);
+
+            for sample in 0..SUBSCANLINES {
+                let y = row as f64 + (sample as f64 + 0.5) / SUBSCANLINES as f64;
+
+                while next_edge < edges.len() && edges[next_edge].top <= y {
+                    active.push(&edges[next_edge]);
+                    next_edge += 1;
+                }
+
+                active.retain(|edge| edge.bottom > y);
+
+                crossings.clear();
+                crossings.extend(
+                    active
+                        .iter()
+                        .filter(|edge| edge.top <= y)
+                        .map(|edge| (edge.x_at(y), edge.winding)),
+                );
+                crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+                let mut winding = 0;
+
+                for pair in crossings.windows(2) {
+                    winding += pair[0].1;
+
+                    let inside = match rule {
+                        FillRule::NonZero => winding != 0,
+                        FillRule::EvenOdd => winding % 2 != 0,
+                    };
+
+                    if inside {
+                        add_span(&mut coverage, pair[0].0, pair[1].0, 1.0 / SUBSCANLINES as f64);
+                    }
+                }
+            }
+
+            for (x, value) in coverage[..self.width].iter().enumerate() {
+                if *value > 0.0 {
+                    self.blend(row * self.width + x, color, *value);
+                }
+            }
+        }
+    }
+}
+
+impl Backend for RasterBackend {
+    fn size(&self) -> (f64, f64) {
+        (self.width as f64, self.height as f64)
+    }
+
+    fn clear(&mut self, color: &Color) {
+        for index in 0..self.pixels.len() {
+            self.blend(index, color, 1.0);
+        }
+    }
+
+    fn set_stroke_style(&mut self, color: &Color, style: &StrokeStyle) {
+        self.stroke_color = *color;
+        self.stroke_style = style.clone();
+    }
+
+    fn set_fill_style(&mut self, color: &Color) {
+        self.fill_color = *color;
+    }
+
+    fn stroke(&mut self, path: &Path) {
+        let style = self.stroke_style.clone();
+        let mut polygons = Vec::new();
+
+        for subpath in path.flatten(TOLERANCE) {
+            let mut points = subpath.points;
+            points.dedup();
+
+            if subpath.closed && points.len() > 1 {
+                points.push(points[0]);
+            }
+
+            match dash(&points, &style.dash, style.dash_offset) {
+                Some(dashes) => {
+                    for points in dashes {
+                        outline(&mut polygons, &points, false, &style);
+                    }
+                }
+                None => outline(&mut polygons, &points, subpath.closed, &style),
+            }
+        }
+
+        let color = self.stroke_color;
+        self.fill_polygons(&polygons, FillRule::NonZero, &color);
+    }
+
+    fn fill(&mut self, path: &Path, rule: FillRule) {
+        let polygons: Vec<Vec<Vec3>> = path
+            .flatten(TOLERANCE)
+            .into_iter()
+            .map(|subpath| subpath.points)
+            .collect();
+
+        let color = self.fill_color;
+        self.fill_polygons(&polygons, rule, &color);
+    }
+
+    fn fill_text(&mut self, text: &Text, placement: &Mat3) {
+        // Capitals are 0.7 em high, and glyphs 0.6 em apart
+        let cell = text.font.size / 10.0;
+        let length = text.content.chars().count();
+        let width = (length * font::ADVANCE).saturating_sub(1) as f64;
+
+        let left = match text.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => -width / 2.0,
+            TextAlign::Right => -width,
+        };
+
+        let top = match text.baseline {
+            TextBaseline::Top => 0.0,
+            TextBaseline::Middle => -(font::GLYPH_HEIGHT as f64) / 2.0,
+            TextBaseline::Alphabetic => -(font::GLYPH_HEIGHT as f64),
+            // Below the descenders of a font that had them
+            TextBaseline::Bottom => -(font::GLYPH_HEIGHT as f64) - 2.0,
+        };
+
+        let mut polygons = Vec::new();
+
+        // Runs of set pixels in each glyph column, as rectangles in cells
+        for (i, character) in text.content.chars().enumerate() {
+            let glyph = font::glyph(character);
+
+            for column in 0..font::GLYPH_WIDTH {
+                let mut row = 0;
+
+                while row < font::GLYPH_HEIGHT {
+                    if !font::is_set(glyph, column, row) {
+                        row += 1;
+                        continue;
+                    }
+
+                    let start = row;
+
+                    while row < font::GLYPH_HEIGHT && font::is_set(glyph, column, row) {
+                        row += 1;
+                    }
+
+                    let x = left + (i * font::ADVANCE + column) as f64;
+                    let (y0, y1) = (top + start as f64, top + row as f64);
+
+                    polygons.push(
+                        [(x, y0), (x + 1.0, y0), (x + 1.0, y1), (x, y1)]
+                            .iter()
+                            .map(|&(x, y)| placement * &vec3!(x * cell, y * cell, 1.0))
+                            .collect(),
+                    );
+                }
+            }
+        }
+
+        let color = self.fill_color;
+        self.fill_polygons(&polygons, FillRule::NonZero, &color);
+    }
+
+    fn begin_layer(&mut self, _name: &str, opacity: f64) {
+        self.opacity = opacity;
+    }
+
+    fn end_layer(&mut self) {
+        self.opacity = 1.0;
+    }
+}
+
+struct Edge {
+    top: f64,
+    bottom: f64,
+    x: f64,
+    slope: f64,
+    winding: i32,
+}
+
+impl Edge {
+    fn new(a: &Vec3, b: &Vec3) -> Self {
+        let (upper, lower, winding) = if a.y < b.y { (a, b, 1) } else { (b, a, -1) };
+
+        Edge {
+            top: upper.y,
+            bottom: lower.y,
+            x: upper.x,
+            slope: (lower.x - upper.x) / (lower.y - upper.y),
+            winding,
+        }
+    }
+
+    fn x_at(&self, y: f64) -> f64 {
+        self.x + (y - self.top) * self.slope
+    }
+}
+
+// Adds `weight` to every pixel in [from, to), with partially covered pixels
+// at both ends getting their share
+fn add_span(coverage: &mut [f64], from: f64, to: f64, weight: f64) {
+    let width = (coverage.len() - 1) as f64;
+    let from = from.clamp(0.0, width);
+    let to = to.clamp(0.0, width);
+
+    if to <= from {
+        return;
+    }
+
+    let first = from.floor() as usize;
+    let last = to.floor() as usize;
+
+    if first == last {
+        coverage[first] += (to - from) * weight;
+        return;
+    }
+
+    coverage[first] += (first as f64 + 1.0 - from) * weight;
+
+    for value in &mut coverage[first + 1..last] {
+        *value += weight;
+    }
+
+    coverage[last] += (to - last as f64) * weight;
+}
+
+// Splits a polyline into dashes like the canvas does, or None when the
+// pattern doesn't dash anything
+fn dash(points: &[Vec3], pattern: &[f64], offset: f64) -> Option<Vec<Vec<Vec3>>> {
+    let total: f64 = pattern.iter().sum();
+
+    if pattern.is_empty() || total <= 0.0 || pattern.iter().any(|length| *length < 0.0 || !length.is_finite()) {
+        return None;
+    }
+
+    // Odd patterns are repeated to get an even number of entries
+    let pattern: Vec<f64> = if pattern.len() % 2 == 1 {
+        pattern.iter().chain(pattern.iter()).copied().collect()
+    } else {
+        pattern.to_vec()
+    };
+
+    let total: f64 = pattern.iter().sum();
+    let mut index = 0;
+    let mut phase = offset.rem_euclid(total);
+
+    while phase >= pattern[index] {
+        phase -= pattern[index];
+        index = (index + 1) % pattern.len();
+    }
+
+    let mut left = pattern[index] - phase;
+    let mut dashes = Vec::new();
+    let mut current: Vec<Vec3> = Vec::new();
+
+    if index % 2 == 0 && !points.is_empty() {
+        current.push(points[0]);
+    }
+
+    for pair in points.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        let length = f64::hypot(b.x - a.x, b.y - a.y);
+        let mut t = 0.0;
+
+        while length - t > left {
+            t += left;
+
+            let k = t / length;
+            let point = vec3!(a.x + (b.x - a.x) * k, a.y + (b.y - a.y) * k, 1.0);
+
+            if index % 2 == 0 {
+                current.push(point);
+                dashes.push(std::mem::take(&mut current));
+            } else {
+                current.push(point);
+            }
+
+            index = (index + 1) % pattern.len();
+            left = pattern[index];
+        }
+
+        left -= length - t;
+
+        if index % 2 == 0 {
+            current.push(*b);
+        }
+    }
+
+    if index % 2 == 0 {
+        dashes.push(current);
+    }
+
+    dashes.retain(|dash| dash.len() > 1);
+
+    Some(dashes)
+}
+
+// Covers the stroke of a polyline with polygons that all wind the same way,
+// so that filling them with the nonzero rule paints their union
+fn outline(polygons: &mut Vec<Vec<Vec3>>, points: &[Vec3], closed: bool, style: &StrokeStyle) {
+    let half = style.width.value() / 2.0;
+
+    let mut points = points.to_vec();
+    points.dedup();
+
+    if points.len() < 2 || half <= 0.0 {
+        return;
+    }
+
+    let mut push = |mut polygon: Vec<Vec3>| {
+        if signed_area(&polygon) < 0.0 {
+            polygon.reverse();
+        }
+
+        polygons.push(polygon);
+    };
+
+    let offset = |point: &Vec3, normal: &Vec3, k: f64| {
+        vec3!(point.x + normal.x * k, point.y + normal.y * k, 1.0)
+    };
+
+    let count = points.len();
+    let closed = closed && count > 2 && points[0] == points[count - 1];
+
+    // Unit direction and left normal of every segment
+    let directions: Vec<(Vec3, Vec3)> = points
+        .windows(2)
+        .map(|pair| {
+            let length = f64::hypot(pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+            let direction = vec3!((pair[1].x - pair[0].x) / length, (pair[1].y - pair[0].y) / length, 0.0);
+            let normal = vec3!(-direction.y, direction.x, 0.0);
+            (direction, normal)
+        })
+        .collect();
+
+    for (pair, (_, normal)) in points.windows(2).zip(&directions) {
+        push(vec![
+            offset(&pair[0], normal, half),
+            offset(&pair[1], normal, half),
+            offset(&pair[1], normal, -half),
+            offset(&pair[0], normal, -half),
+        ]);
+    }
+
+    // Joins between consecutive segments, including the closing one
+    let joins = if closed { count - 1 } else { count - 2 };
+
+    for i in 0..joins {
+        let point = &points[i + 1];
+        let (d0, n0) = &directions[i];
+        let (d1, n1) = &directions[(i + 1) % directions.len()];
+
+        let cross = d0.x * d1.y - d0.y * d1.x;
+
+        if cross.abs() < 1e-12 && d0.x * d1.x + d0.y * d1.y > 0.0 {
+            continue;
+        }
+
+        // The outer side of the turn is the one the join fills in
+        let side = if cross > 0.0 { -half } else { half };
+        let outer0 = offset(point, n0, side);
+        let outer1 = offset(point, n1, side);
+
+        match style.join {
+            LineJoin::Round => push(circle(point, half)),
+            LineJoin::Bevel => push(vec![*point, outer0, outer1]),
+            LineJoin::Miter => {
+                let cosine = n0.x * n1.x + n0.y * n1.y;
+                let ratio = f64::sqrt(2.0 / (1.0 + cosine));
+
+                if cosine > -1.0 && ratio <= style.miter_limit {
+                    let k = side / (1.0 + cosine);
+                    let tip = vec3!(point.x + (n0.x + n1.x) * k, point.y + (n0.y + n1.y) * k, 1.0);
+                    push(vec![*point, outer0, tip, outer1]);
+                } else {
+                    push(vec![*point, outer0, outer1]);
+                }
+            }
+        }
+    }
+
+    if closed {
+        return;
+    }
+
+    let (start_direction, start_normal) = &directions[0];
+    let (end_direction, end_normal) = &directions[directions.len() - 1];
+    let start = &points[0];
+    let end = &points[count - 1];
+
+    match style.cap {
+        LineCap::Butt => {}
+        LineCap::Round => {
+            push(circle(start, half));
+            push(circle(end, half));
+        }
+        LineCap::Square => {
+            let before = offset(start, start_direction, -half);
+            let after = offset(end, end_direction, half);
+
+            push(vec![
+                offset(start, start_normal, half),
+                offset(&before, start_normal, half),
+                offset(&before, start_normal, -half),
+                offset(start, start_normal, -half),
+            ]);
+            push(vec![
+                offset(end, end_normal, half),
+                offset(&after, end_normal, half),
+                offset(&after, end_normal, -half),
+                offset(end, end_normal, -half),
+            ]);
+        }
+    }
+}
+
+fn circle(center: &Vec3, radius: f64) -> Vec<Vec3> {
+    let step = if radius > TOLERANCE {
+        2.0 * f64::acos(1.0 - TOLERANCE / radius)
+    } else {
+        PI / 2.0
+    };
+
+    let segments = ((PI * 2.0 / step).ceil() as usize).clamp(8, 1000);
+
+    (0..segments)
+        .map(|i| {
+            let t = PI * 2.0 * (i as f64) / (segments as f64);
+            vec3!(center.x + radius * f64::cos(t), center.y + radius * f64::sin(t), 1.0)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::ellipse::EllipticalArc;
+
+    const WHITE: Color = Color::rgb(1.0, 1.0, 1.0);
+    const RED: Color = Color::rgb(1.0, 0.0, 0.0);
+
+    fn rectangle(x: f64, y: f64, width: f64, height: f64) -> Path {
+        let mut path = Path::new();
+
+        path.move_to(x, y);
+        path.line_to(x + width, y);
+        path.line_to(x + width, y + height);
+        path.line_to(x, y + height);
+        path.close_path();
+
+        path
+    }
+
+    #[test]
+    fn test_fill_rectangle() {
+        let mut raster = RasterBackend::new(10, 10);
+
+        raster.clear(&WHITE);
+        raster.set_fill_style(&RED);
+        raster.fill(&rectangle(2.0, 2.0, 4.5, 4.0), FillRule::NonZero);
+
+        assert_eq!(raster.pixel(0, 0), [255, 255, 255, 255]);
+        assert_eq!(raster.pixel(3, 3), [255, 0, 0, 255]);
+        // Half covered pixel on the right edge
+        assert_eq!(raster.pixel(6, 3), [255, 128, 128, 255]);
+        assert_eq!(raster.pixel(7, 3), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn test_fill_rules() {
+        let mut path = rectangle(0.0, 0.0, 10.0, 10.0);
+        let inner = rectangle(3.0, 3.0, 4.0, 4.0);
+        path.commands.extend(inner.commands);
+
+        let mut nonzero = RasterBackend::new(10, 10);
+        nonzero.fill(&path, FillRule::NonZero);
+
+        let mut evenodd = RasterBackend::new(10, 10);
+        evenodd.fill(&path, FillRule::EvenOdd);
+
+        assert_eq!(nonzero.pixel(5, 5), [0, 0, 0, 255]);
+        assert_eq!(evenodd.pixel(5, 5), [0, 0, 0, 0]);
+        assert_eq!(evenodd.pixel(1, 1), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_stroke_caps() {
+        let mut path = Path::new();
+        path.move_to(4.0, 5.0);
+        path.line_to(16.0, 5.0);
+
+        let mut butt = RasterBackend::new(20, 10);
+        butt.set_stroke_style(&RED, &StrokeStyle::with_width(LineWidth::Screen(4.0)));
+        butt.stroke(&path);
+
+        let mut square = RasterBackend::new(20, 10);
+        square.set_stroke_style(&RED, &StrokeStyle {
+            cap: LineCap::Square,
+            ..StrokeStyle::with_width(LineWidth::Screen(4.0))
+        });
+        square.stroke(&path);
+
+        assert_eq!(butt.pixel(10, 4), [255, 0, 0, 255]);
+        assert_eq!(butt.pixel(10, 2), [0, 0, 0, 0]);
+        assert_eq!(butt.pixel(3, 5), [0, 0, 0, 0]);
+        assert_eq!(square.pixel(3, 5), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_stroke_overlaps_once() {
+        let mut path = Path::new();
+        path.move_to(2.0, 2.0);
+        path.line_to(18.0, 2.0);
+        path.line_to(2.0, 2.5);
+
+        let mut raster = RasterBackend::new(20, 5);
+        raster.set_stroke_style(&RED.with_alpha(0.5), &StrokeStyle::with_width(LineWidth::Screen(2.0)));
+        raster.stroke(&path);
+
+        assert_eq!(raster.pixel(8, 2), [255, 0, 0, 128]);
+    }
+
+    #[test]
+    fn test_dash() {
+        let points = vec![vec3!(0.0, 0.0, 1.0), vec3!(10.0, 0.0, 1.0)];
+        let dashes = dash(&points, &[3.0], 1.0).unwrap();
+
+        assert_eq!(dashes, vec![
+            vec![vec3!(0.0, 0.0, 1.0), vec3!(2.0, 0.0, 1.0)],
+            vec![vec3!(5.0, 0.0, 1.0), vec3!(8.0, 0.0, 1.0)],
+        ]);
+        assert_eq!(dash(&points, &[], 0.0), None);
+    }
+
+    #[test]
+    fn test_fill_text() {
+        use crate::plotter::{Font, TextSpace};
+
+        let text = Text {
+            anchor: vec3!(0.0, 0.0, 1.0),
+            content: "I?".to_string(),
+            font: Font { family: "sans-serif".to_string(), size: 10.0 },
+            align: TextAlign::Left,
+            baseline: TextBaseline::Top,
+            space: TextSpace::Screen,
+        };
+
+        let mut raster = RasterBackend::new(20, 10);
+        raster.set_fill_style(&RED);
+        raster.fill_text(&text, &Mat3::identity().translate(&vec3!(2.0, 1.0, 1.0)));
+
+        // The stem of the I, one pixel per cell at this size
+        for y in 1..8 {
+            assert_eq!(raster.pixel(4, y), [255, 0, 0, 255]);
+        }
+
+        assert_eq!(raster.pixel(2, 4), [0, 0, 0, 0]);
+        assert_eq!(raster.pixel(4, 8), [0, 0, 0, 0]);
+        // The question mark starts after a blank column
+        assert_eq!(raster.pixel(8, 2), [255, 0, 0, 255]);
+
+        // Centered on the anchor, halfway down the capitals
+        let mut centered = RasterBackend::new(20, 10);
+        centered.fill_text(
+            &Text {
+                content: "I".to_string(),
+                align: TextAlign::Center,
+                baseline: TextBaseline::Middle,
+                ..text
+            },
+            &Mat3::identity().translate(&vec3!(10.5, 5.5, 1.0)),
+        );
+
+        assert_eq!(centered.pixel(10, 2), [0, 0, 0, 255]);
+        assert_eq!(centered.pixel(10, 8), [0, 0, 0, 255]);
+        assert_eq!(centered.pixel(10, 1), [0, 0, 0, 0]);
+        assert_eq!(centered.pixel(10, 9), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_circle_coverage() {
+        let mut path = Path::new();
+        path.ellipse(EllipticalArc::full(vec3!(50.0, 50.0, 1.0), 30.0, 30.0, 0.0));
+
+        let mut raster = RasterBackend::new(100, 100);
+        raster.fill(&path, FillRule::NonZero);
+
+        let area: f64 = raster.pixels().chunks(4).map(|pixel| pixel[3] as f64 / 255.0).sum();
+
+        // Flattening may only shave off a band as thick as the tolerance
+        assert!(f64::abs(area - PI * 30.0 * 30.0) < TOLERANCE * PI * 60.0, "{}", area);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::common::colors::Color;
+use crate::math::mat3::Mat3;
+use crate::plotter::path::Path;
+use crate::plotter::style::{FillRule, StrokeStyle};
+use crate::plotter::Text;
+
+use super::Backend;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum DrawCommand {
+    Clear(Color),
+    SetStrokeStyle(Color, StrokeStyle),
+    SetFillStyle(Color),
+    Stroke(Path),
+    Fill(Path, FillRule),
+    FillText(String, Mat3),
+    BeginLayer(String, f64),
+    EndLayer,
+}
+
+// Keeps every draw call instead of drawing, so that rendering can be
+// checked without a browser
+pub struct RecordingBackend {
+    pub size: (f64, f64),
+    pub commands: Vec<DrawCommand>,
+}
+
+impl RecordingBackend {
+    pub fn new(width: f64, height: f64) -> Self {
+        RecordingBackend {
+            size: (width, height),
+            commands: Vec::new(),
+        }
+    }
+
+    // Commands of the most recent frame only
+    pub fn last_frame(&self) -> &[DrawCommand] {
+        let start = self.commands
+            .iter()
+            .rposition(|command| matches!(command, DrawCommand::Clear(_)))
+            .unwrap_or(0);
+
+        &self.commands[start..]
+    }
+}
+
+impl Backend for RecordingBackend {
+    fn size(&self) -> (f64, f64) {
+        self.size
+    }
+
+    fn resize(&mut self, width: f64, height: f64, _pixel_ratio: f64) {
+        self.size = (width, height);
+    }
+
+    fn clear(&mut self, color: &Color) {
+        self.commands.push(DrawCommand::Clear(*color));
+    }
+
+    fn set_stroke_style(&mut self, color: &Color, style: &StrokeStyle) {
+        self.commands.push(DrawCommand::SetStrokeStyle(*color, style.clone()));
+    }
+
+    fn set_fill_style(&mut self, color: &Color) {
+        self.commands.push(DrawCommand::SetFillStyle(*color));
+    }
+
+    fn stroke(&mut self, path: &Path) {
+        self.commands.push(DrawCommand::Stroke(path.clone()));
+    }
+
+    fn fill(&mut self, path: &Path, rule: FillRule) {
+        self.commands.push(DrawCommand::Fill(path.clone(), rule));
+    }
+
+    fn fill_text(&mut self, text: &Text, placement: &Mat3) {
+        self.commands.push(DrawCommand::FillText(text.content.clone(), placement.clone()));
+    }
+
+    fn begin_layer(&mut self, name: &str, opacity: f64) {
+        self.commands.push(DrawCommand::BeginLayer(name.to_string(), opacity));
+    }
+
+    fn end_layer(&mut self) {
+        self.commands.push(DrawCommand::EndLayer);
+    }
+}
@@ -0,0 +1,324 @@
+use std::fmt::Write;
+
+use crate::common::colors::Color;
+use crate::common::format::number;
+use crate::math::ellipse::EllipticalArc;
+use crate::math::mat3::Mat3;
+use crate::plotter::path::{Path, PathCommand};
+use crate::plotter::style::*;
+use crate::plotter::{Text, TextAlign, TextBaseline};
+
+use super::Backend;
+
+// Writes every draw call as an SVG element. Colors go into separate
+// opacity attributes, since rgba() is not understood by every SVG reader.
+pub struct SvgBackend {
+    size: (f64, f64),
+    body: String,
+    stroke_color: Color,
+    stroke_style: StrokeStyle,
+    fill_color: Color,
+    // Of the current layer, folded into the opacity of every element
+    opacity: f64,
+}
+
+impl SvgBackend {
+    pub fn new(width: f64, height: f64) -> Self {
+        SvgBackend {
+            size: (width, height),
+            body: String::new(),
+            stroke_color: Color::rgb(0.0, 0.0, 0.0),
+            stroke_style: StrokeStyle::default(),
+            fill_color: Color::rgb(0.0, 0.0, 0.0),
+            opacity: 1.0,
+        }
+    }
+
+    pub fn finish(self) -> String {
+        let (width, height) = self.size;
+
+        format!(
+            concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+                "<defs><clipPath id=\"canvas\"><rect width=\"{w}\" height=\"{h}\"/></clipPath></defs>\n",
+                "<g clip-path=\"url(#canvas)\">\n",
+                "{body}",
+                "</g>\n",
+                "</svg>\n",
+            ),
+            w = number(width),
+            h = number(height),
+            body = self.body,
+        )
+    }
+
+    fn paint_attributes(&self, name: &str, color: &Color) -> String {
+        paint_attributes(name, &color.with_alpha(color.a * self.opacity))
+    }
+
+    fn stroke_attributes(&self) -> String {
+        let style = &self.stroke_style;
+
+        let mut attributes = format!(
+            "fill=\"none\" {} stroke-width=\"{}\" stroke-linecap=\"{}\" stroke-linejoin=\"{}\" stroke-miterlimit=\"{}\"",
+            self.paint_attributes("stroke", &self.stroke_color),
+            number(style.width.value()),
+            match style.cap {
+                LineCap::Butt => "butt",
+                LineCap::Round => "round",
+                LineCap::Square => "square",
+            },
+            match style.join {
+                LineJoin::Miter => "miter",
+                LineJoin::Round => "round",
+                LineJoin::Bevel => "bevel",
+            },
+            number(style.miter_limit),
+        );
+
+        if !style.dash.is_empty() {
+            let dash: Vec<String> = style.dash.iter().map(|length| number(*length)).collect();
+
+            write!(
+                attributes,
+                " stroke-dasharray=\"{}\" stroke-dashoffset=\"{}\"",
+                dash.join(" "),
+                number(style.dash_offset),
+            ).unwrap();
+        }
+
+        attributes
+    }
+}
+
+impl Backend for SvgBackend {
+    fn size(&self) -> (f64, f64) {
+        self.size
+    }
+
+    fn clear(&mut self, color: &Color) {
+        let (width, height) = self.size;
+
+        writeln!(
+            self.body,
+            "<rect width=\"{}\" height=\"{}\" {}/>",
+            number(width),
+            number(height),
+            self.paint_attributes("fill", color),
+        ).unwrap();
+    }
+
+    fn set_stroke_style(&mut self, color: &Color, style: &StrokeStyle) {
+        self.stroke_color = *color;
+        self.stroke_style = style.clone();
+    }
+
+    fn set_fill_style(&mut self, color: &Color) {
+        self.fill_color = *color;
+    }
+
+    fn stroke(&mut self, path: &Path) {
+        let attributes = self.stroke_attributes();
+        writeln!(self.body, "<path d=\"{}\" {}/>", path_data(path), attributes).unwrap();
+    }
+
+    fn fill(&mut self, path: &Path, rule: FillRule) {
+        writeln!(
+            self.body,
+            "<path d=\"{}\" {} fill-rule=\"{}\" stroke=\"none\"/>",
+            path_data(path),
+            self.paint_attributes("fill", &self.fill_color),
+            match rule {
+                FillRule::NonZero => "nonzero",
+                FillRule::EvenOdd => "evenodd",
+            },
+        ).unwrap();
+    }
+
+    // The opacity goes on every element rather than the group, so that
+    // overlapping shapes of a faded layer show through each other as they
+    // do on the canvas
+    fn begin_layer(&mut self, name: &str, opacity: f64) {
+        self.opacity = opacity;
+        writeln!(self.body, "<g id=\"{}\">", escape(name)).unwrap();
+    }
+
+    fn end_layer(&mut self) {
+        self.opacity = 1.0;
+        self.body.push_str("</g>\n");
+    }
+
+    fn fill_text(&mut self, text: &Text, placement: &Mat3) {
+        let m = placement;
+
+        writeln!(
+            self.body,
+            "<text transform=\"matrix({} {} {} {} {} {})\" font-family=\"{}\" font-size=\"{}\" text-anchor=\"{}\" dominant-baseline=\"{}\" {}>{}</text>",
+            number(m.m11),
+            number(m.m21),
+            number(m.m12),
+            number(m.m22),
+            number(m.m13),
+            number(m.m23),
+            escape(&text.font.family),
+            number(text.font.size),
+            match text.align {
+                TextAlign::Left => "start",
+                TextAlign::Center => "middle",
+                TextAlign::Right => "end",
+            },
+            match text.baseline {
+                TextBaseline::Top => "text-before-edge",
+                TextBaseline::Middle => "central",
+                TextBaseline::Alphabetic => "alphabetic",
+                TextBaseline::Bottom => "text-after-edge",
+            },
+            self.paint_attributes("fill", &self.fill_color),
+            escape(&text.content),
+        ).unwrap();
+    }
+}
+
+fn paint_attributes(name: &str, color: &Color) -> String {
+    let [r, g, b, a] = color.to_rgba8();
+
+    if a == 255 {
+        format!("{}=\"rgb({}, {}, {})\"", name, r, g, b)
+    } else {
+        format!(
+            "{}=\"rgb({}, {}, {})\" {}-opacity=\"{}\"",
+            name, r, g, b, name, number((a as f64) / 255.0),
+        )
+    }
+}
+
+fn path_data(path: &Path) -> String {
+    let mut data = String::new();
+    let mut has_current_point = false;
+
+    for command in &path.commands {
+        match *command {
+            PathCommand::MoveTo(x, y) => {
+                write!(data, "M{} {}", number(x), number(y)).unwrap();
+            }
+            PathCommand::LineTo(x, y) => {
+                write!(data, "L{} {}", number(x), number(y)).unwrap();
+            }
+            PathCommand::QuadraticCurveTo(cpx, cpy, x, y) => {
+                write!(data, "Q{} {} {} {}", number(cpx), number(cpy), number(x), number(y)).unwrap();
+            }
+            PathCommand::BezierCurveTo(cp1x, cp1y, cp2x, cp2y, x, y) => {
+                write!(
+                    data,
+                    "C{} {} {} {} {} {}",
+                    number(cp1x), number(cp1y), number(cp2x), number(cp2y), number(x), number(y),
+                ).unwrap();
+            }
+            PathCommand::Ellipse(arc) => {
+                let start = arc.point_at(arc.start);
+
+                // Like the canvas, connect the arc to whatever was drawn before
+                let command = if has_current_point { 'L' } else { 'M' };
+                write!(data, "{}{} {}", command, number(start.x), number(start.y)).unwrap();
+
+                write_arc(&mut data, &arc);
+            }
+            PathCommand::ClosePath => data.push('Z'),
+        }
+
+        has_current_point = true;
+    }
+
+    data
+}
+
+// SVG arcs are given by their end points, so the sweep is split in two
+// halves to keep full ellipses from collapsing into nothing.
+fn write_arc(data: &mut String, arc: &EllipticalArc) {
+    let sweep = arc.sweep();
+    let rotation = arc.rotation.to_degrees();
+    let sweep_flag = if sweep > 0.0 { 1 } else { 0 };
+
+    for half in 1..=2 {
+        let end = arc.point_at(arc.start + sweep * (half as f64) / 2.0);
+
+        write!(
+            data,
+            "A{} {} {} 0 {} {} {}",
+            number(arc.rx),
+            number(arc.ry),
+            number(rotation),
+            sweep_flag,
+            number(end.x),
+            number(end.y),
+        ).unwrap();
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::vec3::Vec3;
+    use crate::vec3;
+
+    #[test]
+    fn test_path_data() {
+        let mut path = Path::new();
+
+        path.move_to(0.0, 0.0);
+        path.line_to(10.5, -2.25);
+        path.close_path();
+        path.ellipse(EllipticalArc::full(vec3!(5.0, 5.0, 1.0), 5.0, 5.0, 0.0));
+
+        assert_eq!(
+            path_data(&path),
+            "M0 0L10.5 -2.25ZL10 5A5 5 0 0 1 0 5A5 5 0 0 1 10 5",
+        );
+    }
+
+    #[test]
+    fn test_document() {
+        let mut svg = SvgBackend::new(20.0, 10.0);
+        let mut path = Path::new();
+
+        path.move_to(1.0, 1.0);
+        path.line_to(2.0, 2.0);
+
+        svg.clear(&Color::rgb(1.0, 1.0, 1.0));
+        svg.set_stroke_style(&Color::rgba(1.0, 0.0, 0.0, 0.5), &StrokeStyle::default());
+        svg.stroke(&path);
+
+        let document = svg.finish();
+
+        assert!(document.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"20\" height=\"10\""));
+        assert!(document.contains("<rect width=\"20\" height=\"10\" fill=\"rgb(255, 255, 255)\"/>"));
+        assert!(document.contains("<path d=\"M1 1L2 2\" fill=\"none\" stroke=\"rgb(255, 0, 0)\" stroke-opacity=\"0.502\""));
+    }
+
+    #[test]
+    fn test_layer_opacity() {
+        let mut svg = SvgBackend::new(20.0, 10.0);
+        let mut path = Path::new();
+
+        path.move_to(1.0, 1.0);
+        path.line_to(2.0, 2.0);
+
+        svg.set_fill_style(&Color::rgba(0.0, 0.0, 1.0, 0.5));
+        svg.begin_layer("ink", 0.5);
+        svg.fill(&path, FillRule::NonZero);
+        svg.end_layer();
+        svg.fill(&path, FillRule::NonZero);
+
+        let document = svg.finish();
+
+        assert!(document.contains("<g id=\"ink\">\n<path d=\"M1 1L2 2\" fill=\"rgb(0, 0, 255)\" fill-opacity=\"0.251\""));
+        assert!(document.contains("</g>\n<path d=\"M1 1L2 2\" fill=\"rgb(0, 0, 255)\" fill-opacity=\"0.502\""));
+    }
+}
@@ -0,0 +1,216 @@
+use crate::interop::events::*;
+use crate::math::mat3::Mat3;
+use crate::math::vec3::Vec3;
+use crate::vec3;
+
+use super::backend::Backend;
+use super::Plotter;
+
+// Pans, zooms and rotates the view of a plotter in response to pointer and
+// wheel events. Changes are applied in canvas space on top of whatever
+// transform the plotter already has.
+pub struct Camera {
+    // Limits of the linear scale of the transform
+    pub min_zoom: f64,
+    pub max_zoom: f64,
+    // Zoom factor per pixel of wheel movement, as an exponent
+    pub zoom_speed: f64,
+    // Button that drags the view around
+    pub button: i8,
+    // Dragging while this is held rotates around the middle of the canvas,
+    // no rotation when not set
+    pub rotate_with: Option<Modifier>,
+    drag: Option<Drag>,
+}
+
+#[derive(Copy, Clone)]
+enum Drag {
+    Pan(Vec3),
+    Rotate(Vec3),
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Camera {
+            min_zoom: 0.01,
+            max_zoom: 100.0,
+            zoom_speed: 0.002,
+            button: 0,
+            rotate_with: Some(Modifier::Shift),
+            drag: None,
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    // Whether the event was taken by the camera
+    pub fn dispatch<B: Backend>(&mut self, event: &GlobalEvent, plotter: &mut Plotter<B>) -> bool {
+        match event {
+            GlobalEvent::PointerDown(data) if data.button == self.button => {
+                let position = vec3!(data.x, data.y, 1.0);
+
+                self.drag = Some(match self.rotate_with {
+                    Some(modifier) if data.modifiers.has(modifier) => Drag::Rotate(position),
+                    _ => Drag::Pan(position),
+                });
+
+                true
+            }
+            GlobalEvent::PointerMove(data) => {
+                let position = vec3!(data.x, data.y, 1.0);
+
+                match self.drag {
+                    Some(Drag::Pan(last)) => {
+                        let delta = vec3!(position.x - last.x, position.y - last.y, 1.0);
+                        apply(plotter, Mat3::identity().translate(&delta));
+                        self.drag = Some(Drag::Pan(position));
+                    }
+                    Some(Drag::Rotate(last)) => {
+                        let (width, height) = plotter.canvas_size();
+                        let pivot = vec3!(width / 2.0, height / 2.0, 1.0);
+
+                        let angle = f64::atan2(position.y - pivot.y, position.x - pivot.x)
+                            - f64::atan2(last.y - pivot.y, last.x - pivot.x);
+
+                        // Positive canvas angles turn from x towards y, the
+                        // opposite of `Mat3::rotate`
+                        apply(plotter, around(&pivot, Mat3::identity().rotate(-angle)));
+                        self.drag = Some(Drag::Rotate(position));
+                    }
+                    None => return false,
+                }
+
+                true
+            }
+            GlobalEvent::PointerUp(data) if data.button == self.button && self.drag.is_some() => {
+                self.drag = None;
+                true
+            }
+            GlobalEvent::Wheel(data) => {
+                let zoom = plotter.get_transform().linear_scale();
+
+                if zoom <= 0.0 {
+                    return false;
+                }
+
+                let target = zoom * f64::exp(-data.dy * self.zoom_speed);
+                let factor = target.clamp(self.min_zoom, self.max_zoom) / zoom;
+                let scale = Mat3::identity().scale(&vec3!(factor, factor, 1.0));
+
+                apply(plotter, around(&vec3!(data.px, data.py, 1.0), scale));
+
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+// Canvas space transform that keeps `pivot` in place
+fn around(pivot: &Vec3, transform: Mat3) -> Mat3 {
+    let to_pivot = Mat3::identity().translate(pivot);
+    let from_pivot = Mat3::identity().translate(&vec3!(-pivot.x, -pivot.y, 1.0));
+
+    &(&to_pivot * &transform) * &from_pivot
+}
+
+fn apply<B: Backend>(plotter: &mut Plotter<B>, canvas_transform: Mat3) {
+    let transform = &canvas_transform * plotter.get_transform();
+    plotter.set_transform(transform);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plotter::backend::recording::RecordingBackend;
+    use crate::plotter::test_support::plotter;
+
+    fn pointer(x: f64, y: f64, modifiers: Modifiers) -> PointerEventData {
+        PointerEventData { x, y, button: 0, modifiers }
+    }
+
+    fn wheel(dy: f64, px: f64, py: f64) -> GlobalEvent {
+        GlobalEvent::Wheel(WheelEventData { dx: 0.0, dy, px, py, modifiers: Modifiers::default() })
+    }
+
+    fn drag(
+        camera: &mut Camera,
+        plotter: &mut Plotter<RecordingBackend>,
+        path: &[(f64, f64)],
+        modifiers: Modifiers,
+    ) {
+        let (x, y) = path[0];
+        camera.dispatch(&GlobalEvent::PointerDown(pointer(x, y, modifiers)), plotter);
+
+        for (x, y) in &path[1..] {
+            camera.dispatch(&GlobalEvent::PointerMove(pointer(*x, *y, modifiers)), plotter);
+        }
+
+        let (x, y) = path[path.len() - 1];
+        camera.dispatch(&GlobalEvent::PointerUp(pointer(x, y, modifiers)), plotter);
+    }
+
+    fn close(a: &Vec3, b: &Vec3) -> bool {
+        f64::hypot(a.x - b.x, a.y - b.y) < 1e-9
+    }
+
+    #[test]
+    fn test_pan() {
+        let mut plotter = plotter();
+        let mut camera = Camera::new();
+
+        let path = [(10.0, 10.0), (20.0, 15.0), (40.0, 30.0)];
+        drag(&mut camera, &mut plotter, &path, Modifiers::default());
+
+        assert!(!camera.is_dragging());
+        assert!(close(&plotter.project_to_canvas(&vec3!(0.0, 0.0, 1.0)), &vec3!(30.0, 20.0, 1.0)));
+
+        // Moving without a button held leaves the view alone
+        let hover = GlobalEvent::PointerMove(pointer(0.0, 0.0, Modifiers::default()));
+        assert!(!camera.dispatch(&hover, &mut plotter));
+    }
+
+    #[test]
+    fn test_zoom_around_cursor() {
+        let mut plotter = plotter();
+        let mut camera = Camera::new();
+
+        let cursor = vec3!(30.0, 70.0, 1.0);
+        let under_cursor = plotter.unproject_from_canvas(&cursor);
+
+        camera.dispatch(&wheel(-100.0, cursor.x, cursor.y), &mut plotter);
+
+        assert!(plotter.get_transform().linear_scale() > 1.0);
+        assert!(close(&plotter.project_to_canvas(&under_cursor), &cursor));
+
+        // Zooming stops at the limits
+        for _ in 0..100 {
+            camera.dispatch(&wheel(1000.0, cursor.x, cursor.y), &mut plotter);
+        }
+
+        assert!((plotter.get_transform().linear_scale() - camera.min_zoom).abs() < 1e-9);
+        assert!(close(&plotter.project_to_canvas(&under_cursor), &cursor));
+    }
+
+    #[test]
+    fn test_rotate() {
+        let mut plotter = plotter();
+        let mut camera = Camera::new();
+        let shift = Modifiers { shift: true, ..Modifiers::default() };
+
+        // A quarter turn around the middle of the canvas
+        drag(&mut camera, &mut plotter, &[(100.0, 50.0), (50.0, 100.0)], shift);
+
+        assert!(close(&plotter.project_to_canvas(&vec3!(50.0, 50.0, 1.0)), &vec3!(50.0, 50.0, 1.0)));
+        assert!(close(&plotter.project_to_canvas(&vec3!(100.0, 50.0, 1.0)), &vec3!(50.0, 100.0, 1.0)));
+
+        // Without rotation the same drag pans
+        let mut plotter = self::plotter();
+        camera.rotate_with = None;
+        drag(&mut camera, &mut plotter, &[(100.0, 50.0), (50.0, 100.0)], shift);
+
+        assert!(close(&plotter.project_to_canvas(&vec3!(0.0, 0.0, 1.0)), &vec3!(-50.0, 50.0, 1.0)));
+    }
+}
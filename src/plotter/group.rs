@@ -0,0 +1,35 @@
+use std::collections::BTreeSet;
+
+use crate::math::mat3::Mat3;
+
+// Reference to a group of a plotter. Groups are never removed, so it stays
+// valid for as long as the plotter does.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct GroupId(pub(super) u32);
+
+// Node of the scene graph. Primitives in a group are drawn in its world
+// space, the composition of its own transform and those of its ancestors.
+pub(super) struct Group {
+    pub parent: Option<GroupId>,
+    pub children: Vec<GroupId>,
+    pub transform: Mat3,
+    // Valid unless stale, which it becomes whenever the transform of the
+    // group or of any of its ancestors changes
+    pub world: Mat3,
+    pub stale: bool,
+    // Slots of the primitives in the group, not including subgroups
+    pub members: BTreeSet<u32>,
+}
+
+impl Group {
+    pub fn new(parent: Option<GroupId>, transform: Mat3) -> Self {
+        Group {
+            parent,
+            children: Vec::new(),
+            world: transform.clone(),
+            transform,
+            stale: true,
+            members: BTreeSet::new(),
+        }
+    }
+}
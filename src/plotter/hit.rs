@@ -0,0 +1,165 @@
+use crate::math::clip::winding_number;
+use crate::math::distance::{distance_to_line, distance_to_polyline};
+use crate::math::vec3::Vec3;
+
+use super::path::Path;
+use super::projection::{Projection, POINT_RADIUS};
+use super::style::{FillRule, LineWidth};
+use super::{Primitive, Shape};
+
+// Curves are measured against lines this close to them, in pixels
+const FLATTENING: f64 = 0.1;
+
+// Whether `point`, in world space, touches the primitive as it is drawn.
+// `pixel` is the world size of a canvas pixel, and the stroke counts as
+// touched up to `tolerance` pixels away from its edge.
+pub fn hit(primitive: &Primitive, point: &Vec3, tolerance: f64, pixel: f64) -> bool {
+    let half_width = match primitive.stroke.map(|_| primitive.style.width) {
+        Some(LineWidth::Screen(width)) => width / 2.0 * pixel,
+        Some(LineWidth::World(width)) => width / 2.0,
+        None => 0.0,
+    };
+
+    let reach = tolerance * pixel + half_width;
+
+    match &primitive.shape {
+        Shape::Point(center) => {
+            f64::hypot(point.x - center.x, point.y - center.y) <= (POINT_RADIUS + tolerance) * pixel
+        }
+        Shape::Segment(from, to) => {
+            distance_to_line(point, from, &(to - from), 0.0, 1.0) <= reach
+        }
+        Shape::Ray(origin, direction) => {
+            distance_to_line(point, origin, direction, 0.0, f64::INFINITY) <= reach
+        }
+        Shape::Line(from, to) => {
+            distance_to_line(point, from, &(to - from), f64::NEG_INFINITY, f64::INFINITY) <= reach
+        }
+        // Without font metrics there is nothing to measure text against,
+        // and the grid covers the whole canvas
+        Shape::Text(_) | Shape::Grid(_) => false,
+        shape => {
+            // Tracing with the identity transform keeps the path in world units
+            let mut path = Path::new();
+            Projection::new().trace(shape, &mut path);
+
+            let subpaths = path.flatten(FLATTENING * pixel);
+
+            let near_outline = subpaths
+                .iter()
+                .any(|subpath| distance_to_polyline(point, &subpath.points, subpath.closed) <= reach);
+
+            let inside = match primitive.fill.filter(|_| shape.is_closed()) {
+                Some(fill) => {
+                    let winding: i32 = subpaths
+                        .iter()
+                        .map(|subpath| winding_number(point, &subpath.points))
+                        .sum();
+
+                    match fill.rule {
+                        FillRule::NonZero => winding != 0,
+                        FillRule::EvenOdd => winding % 2 != 0,
+                    }
+                }
+                None => false,
+            };
+
+            inside || (primitive.stroke.is_some() && near_outline)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::colors::Color;
+    use crate::plotter::style::{Fill, StrokeStyle};
+    use crate::plotter::test_support::primitive;
+    use crate::vec3;
+
+    fn filled(shape: Shape) -> Primitive {
+        Primitive {
+            stroke: None,
+            fill: Some(Fill { color: Color::rgb(0.0, 0.0, 0.0), rule: FillRule::NonZero }),
+            ..primitive(shape)
+        }
+    }
+
+    #[test]
+    fn test_hit_point() {
+        let point = primitive(Shape::Point(vec3!(10.0, 10.0, 1.0)));
+
+        assert!(hit(&point, &vec3!(14.0, 10.0, 1.0), 2.0, 1.0));
+        assert!(!hit(&point, &vec3!(16.0, 10.0, 1.0), 2.0, 1.0));
+        // Points keep their size on screen, so zooming in shrinks them in the world
+        assert!(!hit(&point, &vec3!(14.0, 10.0, 1.0), 2.0, 0.5));
+    }
+
+    #[test]
+    fn test_hit_lines() {
+        let segment = primitive(Shape::Segment(vec3!(0.0, 0.0, 1.0), vec3!(10.0, 0.0, 1.0)));
+        let ray = primitive(Shape::Ray(vec3!(0.0, 0.0, 1.0), vec3!(1.0, 0.0, 0.0)));
+        let line = primitive(Shape::Line(vec3!(0.0, 0.0, 1.0), vec3!(1.0, 0.0, 1.0)));
+
+        assert!(hit(&segment, &vec3!(5.0, 2.0, 1.0), 2.0, 1.0));
+        assert!(!hit(&segment, &vec3!(5.0, 3.0, 1.0), 2.0, 1.0));
+        assert!(!hit(&segment, &vec3!(20.0, 0.0, 1.0), 2.0, 1.0));
+
+        assert!(hit(&ray, &vec3!(1000.0, 1.0, 1.0), 2.0, 1.0));
+        assert!(!hit(&ray, &vec3!(-10.0, 0.0, 1.0), 2.0, 1.0));
+        assert!(hit(&line, &vec3!(-1000.0, 1.0, 1.0), 2.0, 1.0));
+    }
+
+    #[test]
+    fn test_hit_world_line_width() {
+        let segment = Primitive {
+            style: StrokeStyle::with_width(LineWidth::World(10.0)),
+            ..primitive(Shape::Segment(vec3!(0.0, 0.0, 1.0), vec3!(10.0, 0.0, 1.0)))
+        };
+
+        assert!(hit(&segment, &vec3!(5.0, 5.5, 1.0), 1.0, 1.0));
+        assert!(!hit(&segment, &vec3!(5.0, 6.5, 1.0), 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_hit_closed_shapes() {
+        let circle = || Shape::Circle { center: vec3!(0.0, 0.0, 1.0), radius: 10.0 };
+        let inside = vec3!(3.0, 3.0, 1.0);
+        let edge = vec3!(0.0, 10.5, 1.0);
+
+        assert!(!hit(&primitive(circle()), &inside, 1.0, 1.0));
+        assert!(hit(&primitive(circle()), &edge, 1.0, 1.0));
+        assert!(hit(&filled(circle()), &inside, 1.0, 1.0));
+        assert!(!hit(&filled(circle()), &edge, 1.0, 1.0));
+
+        let square = Primitive {
+            fill: Some(Fill { color: Color::rgb(0.0, 0.0, 0.0), rule: FillRule::EvenOdd }),
+            ..filled(Shape::Polygon(vec![
+                vec3!(0.0, 0.0, 1.0),
+                vec3!(10.0, 0.0, 1.0),
+                vec3!(10.0, 10.0, 1.0),
+                vec3!(0.0, 10.0, 1.0),
+            ]))
+        };
+
+        assert!(hit(&square, &vec3!(5.0, 5.0, 1.0), 1.0, 1.0));
+        assert!(!hit(&square, &vec3!(15.0, 5.0, 1.0), 1.0, 1.0));
+    }
+
+    #[test]
+    fn test_hit_curves() {
+        let arc = primitive(Shape::Arc { center: vec3!(0.0, 0.0, 1.0), radius: 10.0, start: 0.0, end: 1.0 });
+        let curve = primitive(Shape::QuadraticBezier(
+            vec3!(0.0, 0.0, 1.0),
+            vec3!(10.0, 20.0, 1.0),
+            vec3!(20.0, 0.0, 1.0),
+        ));
+
+        assert!(hit(&arc, &vec3!(f64::cos(0.5) * 10.0, f64::sin(0.5) * 10.0, 1.0), 1.0, 1.0));
+        assert!(!hit(&arc, &vec3!(-10.0, 0.0, 1.0), 1.0, 1.0));
+
+        // The curve peaks halfway between its ends and its control point
+        assert!(hit(&curve, &vec3!(10.0, 10.0, 1.0), 1.0, 1.0));
+        assert!(!hit(&curve, &vec3!(10.0, 12.0, 1.0), 1.0, 1.0));
+    }
+}
@@ -0,0 +1,140 @@
+use crate::common::colors::Color;
+use crate::math::vec3::Vec3;
+
+use super::layer::LayerId;
+use super::style::{Fill, FillRule, LineWidth, StrokeStyle};
+use super::{Primitive, Shape};
+
+// Primitives drawn for a single frame. They are kept after the frame is
+// rendered, so that it can still be exported, and dropped as soon as the
+// next frame starts.
+pub struct Frame {
+    pub(super) primitives: Vec<(Option<LayerId>, Primitive)>,
+    // The primitives were rendered, and belong to the previous frame
+    pub(super) shown: bool,
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Frame {
+            primitives: Vec::new(),
+            shown: false,
+        }
+    }
+
+    // Called before anything is added or rendered
+    pub(super) fn begin(&mut self) {
+        if self.shown {
+            self.primitives.clear();
+            self.shown = false;
+        }
+    }
+}
+
+// Entry point of the immediate drawing calls, see Plotter::draw
+pub struct Draw<'a> {
+    frame: &'a mut Frame,
+}
+
+impl<'a> Draw<'a> {
+    pub(super) fn new(frame: &'a mut Frame) -> Self {
+        frame.begin();
+        Draw { frame }
+    }
+
+    pub fn shape(self, shape: Shape) -> DrawnPrimitive<'a> {
+        DrawnPrimitive {
+            frame: self.frame,
+            layer: None,
+            primitive: Primitive {
+                shape,
+                z_index: 0,
+                stroke: Some(Color::rgb(0.0, 0.0, 0.0)),
+                fill: None,
+                style: StrokeStyle::default(),
+            },
+        }
+    }
+
+    pub fn point(self, point: Vec3) -> DrawnPrimitive<'a> {
+        self.shape(Shape::Point(point))
+    }
+
+    pub fn segment(self, from: Vec3, to: Vec3) -> DrawnPrimitive<'a> {
+        self.shape(Shape::Segment(from, to))
+    }
+
+    pub fn ray(self, origin: Vec3, direction: Vec3) -> DrawnPrimitive<'a> {
+        self.shape(Shape::Ray(origin, direction))
+    }
+
+    pub fn line(self, from: Vec3, to: Vec3) -> DrawnPrimitive<'a> {
+        self.shape(Shape::Line(from, to))
+    }
+
+    pub fn circle(self, center: Vec3, radius: f64) -> DrawnPrimitive<'a> {
+        self.shape(Shape::Circle { center, radius })
+    }
+
+    pub fn polyline(self, points: Vec<Vec3>) -> DrawnPrimitive<'a> {
+        self.shape(Shape::Polyline(points))
+    }
+
+    pub fn polygon(self, points: Vec<Vec3>) -> DrawnPrimitive<'a> {
+        self.shape(Shape::Polygon(points))
+    }
+}
+
+// Primitive of the current frame, which is added once it is dropped. It is
+// stroked in black, on no layer and at z_index 0 unless told otherwise.
+pub struct DrawnPrimitive<'a> {
+    frame: &'a mut Frame,
+    layer: Option<LayerId>,
+    primitive: Primitive,
+}
+
+impl<'a> DrawnPrimitive<'a> {
+    pub fn color(mut self, color: Color) -> Self {
+        self.primitive.stroke = Some(color);
+        self
+    }
+
+    // Fills closed shapes, and leaves out the stroke unless a color is set afterwards
+    pub fn fill(mut self, color: Color) -> Self {
+        self.primitive.fill = Some(Fill { color, rule: FillRule::NonZero });
+        self.primitive.stroke = None;
+        self
+    }
+
+    pub fn width(mut self, width: LineWidth) -> Self {
+        self.primitive.style.width = width;
+        self
+    }
+
+    pub fn style(mut self, style: StrokeStyle) -> Self {
+        self.primitive.style = style;
+        self
+    }
+
+    pub fn z_index(mut self, z_index: i32) -> Self {
+        self.primitive.z_index = z_index;
+        self
+    }
+
+    pub fn layer(mut self, layer: LayerId) -> Self {
+        self.layer = Some(layer);
+        self
+    }
+}
+
+impl Drop for DrawnPrimitive<'_> {
+    fn drop(&mut self) {
+        let primitive = Primitive {
+            shape: std::mem::replace(&mut self.primitive.shape, Shape::Polyline(Vec::new())),
+            style: std::mem::take(&mut self.primitive.style),
+            ..self.primitive
+        };
+
+        self.frame.primitives.push((self.layer, primitive));
+    }
+}
@@ -0,0 +1,33 @@
+use crate::math::mat3::Mat3;
+
+// Reference to a layer of a plotter. Layers are never removed, so it
+// stays valid for as long as the plotter does.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct LayerId(pub(super) u32);
+
+// Named group of primitives that is shown, faded and moved as a whole. Each
+// layer is drawn in one go, and is one group or one pen when exported.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Layer {
+    pub name: String,
+    pub visible: bool,
+    // Fades each primitive of the layer, on top of its own colors
+    pub opacity: f64,
+    // Places the layer within the world, under the plotter transform
+    pub transform: Option<Mat3>,
+    // Layers are drawn by z_index, then in the order they were added. The
+    // z_index of a primitive only orders it within its own layer.
+    pub z_index: i32,
+}
+
+impl Layer {
+    pub fn new(name: &str) -> Self {
+        Layer {
+            name: name.to_string(),
+            visible: true,
+            opacity: 1.0,
+            transform: None,
+            z_index: 0,
+        }
+    }
+}
@@ -1,202 +1,1102 @@
-use std::cmp::Ordering;
-use wasm_bindgen::{JsCast, JsValue};
-use web_sys::CanvasRenderingContext2d;
 use web_sys::HtmlCanvasElement;
 
+use crate::common::colors::Color;
+use crate::math::bounds::Bounds;
 use crate::interop::log::*;
 use crate::math::mat3::Mat3;
 use crate::math::vec3::Vec3;
+use crate::vec3;
+
+use backend::Backend;
+use backend::canvas::CanvasBackend;
+use backend::pen::PenBackend;
+use backend::raster::RasterBackend;
+use backend::svg::SvgBackend;
+use group::GroupId;
+use immediate::{Draw, Frame};
+use layer::{Layer, LayerId};
+use path::Path;
+use pen::gcode::GcodeOptions;
+use pen::optimize::{optimize, pen_up_distance, TravelReport};
+use pen::{gcode, hpgl, PenPath, Plot, PlotOptions};
+use projection::Projection;
+use scene::*;
+use style::*;
+
+pub mod backend;
+pub mod camera;
+pub mod group;
+pub mod hit;
+pub mod immediate;
+pub mod layer;
+pub mod path;
+pub mod pen;
+pub mod projection;
+pub mod quadtree;
+pub mod scene;
+pub mod style;
+#[cfg(test)]
+pub(crate) mod test_support;
 
 #[derive(PartialEq)]
 pub enum Shape {
     Point(Vec3),
     Segment(Vec3, Vec3),
     Ray(Vec3, Vec3),
-    Grid(f64),
+    Line(Vec3, Vec3),
+    Circle { center: Vec3, radius: f64 },
+    Arc { center: Vec3, radius: f64, start: f64, end: f64 },
+    Ellipse { center: Vec3, rx: f64, ry: f64, rotation: f64 },
+    Polyline(Vec<Vec3>),
+    Polygon(Vec<Vec3>),
+    QuadraticBezier(Vec3, Vec3, Vec3),
+    CubicBezier(Vec3, Vec3, Vec3, Vec3),
+    Text(Text),
+    Grid(Grid),
+}
+
+// Infinite grid of lines at multiples of the step, through the world origin
+#[derive(Copy, Clone, PartialEq)]
+pub struct Grid {
+    // Spacing of the major lines, in world units
+    pub step: f64,
+    // Minor lines per major step, 1 for none. Adaptive grids also use it as
+    // the factor between levels.
+    pub subdivisions: u32,
+    // Moves between levels with the zoom, so that lines never get too
+    // dense, and fades the minor lines in and out
+    pub adaptive: bool,
+    // The stroke color is used for minor lines when not set
+    pub minor_color: Option<Color>,
+    // Highlights the lines through the origin when set
+    pub axis_color: Option<Color>,
+}
+
+impl Grid {
+    pub fn new(step: f64) -> Self {
+        Grid {
+            step,
+            subdivisions: 1,
+            adaptive: false,
+            minor_color: None,
+            axis_color: None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum TextBaseline {
+    Top,
+    Middle,
+    Alphabetic,
+    Bottom,
+}
+
+// Screen space text keeps its size and orientation regardless of the
+// transform, world space text is rotated and scaled along with the scene.
+#[derive(Copy, Clone, PartialEq)]
+pub enum TextSpace {
+    Screen,
+    World,
+}
+
+#[derive(PartialEq)]
+pub struct Font {
+    pub family: String,
+    // In pixels for screen space text and in world units otherwise
+    pub size: f64,
+}
+
+#[derive(PartialEq)]
+pub struct Text {
+    pub anchor: Vec3,
+    pub content: String,
+    pub font: Font,
+    pub align: TextAlign,
+    pub baseline: TextBaseline,
+    pub space: TextSpace,
+}
+
+impl Shape {
+    pub fn is_closed(&self) -> bool {
+        matches!(self, Shape::Circle { .. } | Shape::Ellipse { .. } | Shape::Polygon(_))
+    }
+
+    // World space box around the shape, or None when it has no bounds or
+    // they are not known, e.g. for lines and text
+    pub fn bounds(&self) -> Option<Bounds> {
+        match self {
+            Shape::Point(point) => Some(Bounds::around(point, 0.0)),
+            Shape::Segment(from, to) => Bounds::from_points([from, to]),
+            Shape::Circle { center, radius } | Shape::Arc { center, radius, .. } => {
+                Some(Bounds::around(center, *radius)).filter(Bounds::is_finite)
+            }
+            Shape::Ellipse { center, rx, ry, rotation } => {
+                let (sin, cos) = f64::sin_cos(*rotation);
+                let half_width = f64::hypot(rx * cos, ry * sin);
+                let half_height = f64::hypot(rx * sin, ry * cos);
+
+                Some(Bounds {
+                    min_x: center.x - half_width,
+                    min_y: center.y - half_height,
+                    max_x: center.x + half_width,
+                    max_y: center.y + half_height,
+                }).filter(Bounds::is_finite)
+            }
+            Shape::Polyline(points) | Shape::Polygon(points) => Bounds::from_points(points),
+            // Curves stay within their control points
+            Shape::QuadraticBezier(p0, p1, p2) => Bounds::from_points([p0, p1, p2]),
+            Shape::CubicBezier(p0, p1, p2, p3) => Bounds::from_points([p0, p1, p2, p3]),
+            Shape::Ray(..) | Shape::Line(..) | Shape::Text(_) | Shape::Grid(_) => None,
+        }
+    }
+
+    // The point that places the shape as a whole, if it has one
+    pub fn position_mut(&mut self) -> Option<&mut Vec3> {
+        match self {
+            Shape::Point(point) => Some(point),
+            Shape::Circle { center, .. } | Shape::Arc { center, .. } | Shape::Ellipse { center, .. } => {
+                Some(center)
+            }
+            Shape::Text(text) => Some(&mut text.anchor),
+            _ => None,
+        }
+    }
+
+    // Points and control points in drawing order. The direction of a ray is
+    // not a vertex.
+    pub fn vertex_mut(&mut self, index: usize) -> Option<&mut Vec3> {
+        match self {
+            Shape::Point(point) if index == 0 => Some(point),
+            Shape::Ray(origin, _) if index == 0 => Some(origin),
+            Shape::Segment(from, to) | Shape::Line(from, to) => {
+                IntoIterator::into_iter([from, to]).nth(index)
+            }
+            Shape::Polyline(points) | Shape::Polygon(points) => points.get_mut(index),
+            Shape::QuadraticBezier(p0, p1, p2) => IntoIterator::into_iter([p0, p1, p2]).nth(index),
+            Shape::CubicBezier(p0, p1, p2, p3) => IntoIterator::into_iter([p0, p1, p2, p3]).nth(index),
+            _ => None,
+        }
+    }
 }
 
 #[derive(PartialEq)]
 pub struct Primitive {
     pub shape: Shape,
     pub z_index: i32,
-    pub color: JsValue,
+    pub stroke: Option<Color>,
+    // Only closed shapes are filled
+    pub fill: Option<Fill>,
+    pub style: StrokeStyle,
 }
 
-pub struct Plotter {
-    canvas: HtmlCanvasElement,
-    canvas_size: (f64, f64),
-    context: CanvasRenderingContext2d,
-    primitives: Vec<Primitive>,
-    draw_order: Vec<usize>,
-    transform: Mat3,
-    inverse_transform: Mat3,
-    clear_color: JsValue,
+impl Primitive {
+    // Color for points and text, which are neither stroked nor filled as paths
+    pub fn ink(&self) -> Option<Color> {
+        self.stroke.or_else(|| self.fill.map(|fill| fill.color))
+    }
+
+    // Everything drawn in world units, including world space strokes
+    pub fn bounds(&self) -> Option<Bounds> {
+        let bounds = self.shape.bounds()?;
+
+        match (self.stroke, self.style.width) {
+            (Some(_), LineWidth::World(_)) => Some(bounds.expand(self.style.reach())),
+            _ => Some(bounds),
+        }
+    }
+
+    // How far the drawing extends past the bounds in pixels, whatever the transform
+    pub fn screen_reach(&self) -> f64 {
+        match (&self.shape, self.stroke, self.style.width) {
+            (Shape::Point(_), _, _) => projection::POINT_RADIUS,
+            (_, Some(_), LineWidth::Screen(_)) => self.style.reach(),
+            _ => 0.0,
+        }
+    }
+}
+
+pub struct Plotter<B: Backend = CanvasBackend> {
+    backend: B,
+    scene: Scene,
+    projection: Projection,
+    // Device pixels per CSS pixel. The projection and everything drawn
+    // through it stay in CSS pixels.
+    pixel_ratio: f64,
+    // Immediate primitives
+    frame: Frame,
+    clear_color: Color,
+    // Something that shows changed since the last render, which is skipped
+    // otherwise
+    changed: bool,
 }
 
 impl Plotter {
     pub fn new(canvas: HtmlCanvasElement) -> Self {
-        let context = canvas
-            .get_context("2d")
-            .unwrap()
-            .unwrap()
-            .dyn_into::<web_sys::CanvasRenderingContext2d>()
-            .unwrap();
+        Plotter::with_backend(CanvasBackend::new(canvas))
+    }
+}
 
+impl<B: Backend> Plotter<B> {
+    pub fn with_backend(backend: B) -> Self {
         Plotter {
-            canvas,
-            canvas_size: (0.0, 0.0),
-            context,
-            primitives: Vec::new(),
-            draw_order: Vec::new(),
-            transform: Mat3::identity(),
-            inverse_transform: Mat3::identity(),
-            clear_color: "white".into(),
+            backend,
+            scene: Scene::new(),
+            projection: Projection::new(),
+            pixel_ratio: 1.0,
+            frame: Frame::new(),
+            clear_color: Color::rgb(1.0, 1.0, 1.0),
+            changed: true,
         }
     }
 
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    // Anything may be drawn to the backend, so the next render redraws
+    pub fn backend_mut(&mut self) -> &mut B {
+        self.changed = true;
+        &mut self.backend
+    }
+
     pub fn get_transform(&self) -> &Mat3 {
-        &self.transform
+        self.projection.transform()
     }
 
     pub fn set_transform(&mut self, transform: Mat3) {
-        let mut inverse_transform = transform.clone();
-        inverse_transform.inverse();
+        if transform != *self.projection.transform() {
+            self.projection.set_transform(transform);
+            self.changed = true;
+        }
+    }
+
+    pub fn set_clear_color(&mut self, color: &Color) {
+        if *color != self.clear_color {
+            self.clear_color = *color;
+            self.changed = true;
+        }
+    }
 
-        self.transform = transform;
-        self.inverse_transform = inverse_transform;
+    // In CSS pixels
+    pub fn canvas_size(&self) -> (f64, f64) {
+        self.projection.canvas_size()
     }
 
-    pub fn set_clear_color(&mut self, color: &Vec3) {
-        self.clear_color = color.as_rgb_string().into();
+    pub fn pixel_ratio(&self) -> f64 {
+        self.pixel_ratio
+    }
+
+    // Sizes the canvas to `width` by `height` CSS pixels, with a backing
+    // store that matches the device pixel ratio
+    pub fn resize(&mut self, width: f64, height: f64, pixel_ratio: f64) {
+        self.pixel_ratio = if pixel_ratio > 0.0 { pixel_ratio } else { 1.0 };
+        self.backend.resize(width, height, self.pixel_ratio);
+        self.projection.set_canvas_size((width, height));
+        // Resizing the canvas clears it
+        self.changed = true;
     }
 
     pub fn update_canvas_size(&mut self) {
-        self.canvas_size = (
-            self.canvas.width().into(),
-            self.canvas.height().into()
-        );
+        let size = self.backend.size();
+
+        if size != self.projection.canvas_size() {
+            self.projection.set_canvas_size(size);
+            self.changed = true;
+        }
+    }
+
+    // Makes the next render draw even if nothing seems to have changed, e.g.
+    // after the canvas was drawn over
+    pub fn request_redraw(&mut self) {
+        self.changed = true;
+    }
+
+    pub fn needs_redraw(&self) -> bool {
+        // Immediate primitives of the last frame are gone unless drawn again
+        self.changed || (self.frame.shown && !self.frame.primitives.is_empty())
     }
 
-    pub fn render(&self) {
-        self.clear();
+    // Draws the scene, unless it still shows the same as after the last render
+    pub fn render(&mut self) {
+        if !self.needs_redraw() {
+            return;
+        }
+
+        self.scene.update();
+        self.frame.begin();
+
+        draw(&self.scene, &self.frame, &self.projection, &self.clear_color, &mut self.backend);
+        self.frame.shown = true;
+        self.changed = false;
+    }
+
+    // Draws the current frame with some other backend, e.g. for export
+    pub fn render_to<T: Backend>(&mut self, backend: &mut T) {
+        self.scene.update();
+        draw(&self.scene, &self.frame, &self.projection, &self.clear_color, backend);
+    }
+
+    // Adds a primitive to the current frame only. It is drawn by the next
+    // render, and gone from the one after.
+    pub fn draw(&mut self) -> Draw<'_> {
+        self.changed = true;
+        Draw::new(&mut self.frame)
+    }
+
+    // Current frame as a standalone SVG document, in canvas pixels
+    pub fn export_svg(&mut self) -> String {
+        let (width, height) = self.projection.canvas_size();
+        let mut svg = SvgBackend::new(width, height);
 
-        for primitive_index in &self.draw_order {
-            let primitive = &self.primitives[*primitive_index];
+        self.render_to(&mut svg);
+        svg.finish()
+    }
+
+    // Current frame rendered without the browser, as a PNG file. Text is drawn
+    // with the built-in bitmap font rather than the font of the primitive.
+    pub fn export_png(&mut self) -> Vec<u8> {
+        let (width, height) = self.projection.canvas_size();
+        let mut raster = RasterBackend::new(width.round() as usize, height.round() as usize);
+
+        self.render_to(&mut raster);
+        raster.to_png()
+    }
+
+    // Strokes and points of the current frame for a pen plotter, in page millimeters
+    pub fn pen_paths(&mut self, options: &PlotOptions) -> Vec<PenPath> {
+        let (width, height) = self.projection.canvas_size();
+        let page = options.page.transform((width, height));
+        let tolerance = options.tolerance / page.linear_scale();
+        let mut pen = PenBackend::new(width, height, tolerance, options.points, options.layer_pens);
+
+        self.render_to(&mut pen);
+
+        pen.paths
+            .into_iter()
+            .map(|path| PenPath {
+                points: path.points.iter().map(|point| &page * point).collect(),
+                ..path
+            })
+            .collect()
+    }
 
-            self.context.set_fill_style(&primitive.color);
-            self.context.set_stroke_style(&primitive.color);
+    // Pen paths in the order they get plotted
+    pub fn plot(&mut self, options: &PlotOptions) -> Plot {
+        let paths = self.pen_paths(options);
 
-            match primitive.shape {
-                Shape::Point(origin) => self.render_point(&origin),
-                Shape::Segment(from, to) => self.render_segment(&from, &to),
-                Shape::Grid(step) => self.render_grid(step),
-                _ => (),
+        match &options.optimize {
+            Some(optimize_options) => {
+                let (paths, travel) = optimize(paths, optimize_options);
+                Plot { paths, travel }
+            }
+            None => {
+                let distance = pen_up_distance(&paths);
+                Plot { paths, travel: TravelReport { before: distance, after: distance } }
             }
         }
     }
 
-    pub fn add_primitive(&mut self, primitive: Primitive) -> usize {
-        let last_index = self.primitives.len();
-        self.primitives.push(primitive);
-        self.calc_draw_order();
-        last_index
+    pub fn export_hpgl(&mut self, options: &PlotOptions) -> String {
+        hpgl::export(&self.plot(options).paths)
     }
 
-    pub fn get_mut(&mut self, primitive_index: usize) -> &mut Primitive {
-        &mut self.primitives[primitive_index]
+    pub fn export_gcode(&mut self, options: &GcodeOptions) -> String {
+        gcode::export(&self.plot(&options.plot).paths, options)
     }
 
-    fn calc_draw_order(&mut self) {
-        let len = self.primitives.len();
-        self.draw_order.resize(len, 0);
+    pub fn add_primitive(&mut self, primitive: Primitive) -> PrimitiveHandle {
+        self.changed = true;
+        self.scene.add(primitive)
+    }
 
-        for i in 0 .. len {
-            self.draw_order[i] = i;
-        }
+    pub fn add_primitive_to(&mut self, layer: LayerId, primitive: Primitive) -> PrimitiveHandle {
+        self.changed = true;
+        self.scene.add_to(Some(layer), primitive)
+    }
 
-        let primitives = &self.primitives;
+    pub fn add_layer(&mut self, layer: Layer) -> LayerId {
+        self.scene.add_layer(layer)
+    }
 
-        self.draw_order.sort_by(|&a, &b| -> Ordering {
-            let z_a = primitives[a].z_index;
-            let z_b = primitives[b].z_index;
-            z_a.cmp(&z_b)
-        });
+    pub fn layer(&self, id: LayerId) -> Option<&Layer> {
+        self.scene.layer(id)
+    }
+
+    pub fn layer_mut(&mut self, id: LayerId) -> Option<&mut Layer> {
+        self.changed = true;
+        self.scene.layer_mut(id)
+    }
+
+    pub fn find_layer(&self, name: &str) -> Option<LayerId> {
+        self.scene.find_layer(name)
+    }
+
+    // Groups nest within their parent. `transform` maps the space of the
+    // group into the one of the parent, or into the world for top groups.
+    pub fn add_group(&mut self, parent: Option<GroupId>, transform: Mat3) -> GroupId {
+        self.scene.add_group(parent, transform)
+    }
+
+    pub fn group_transform(&self, id: GroupId) -> Option<&Mat3> {
+        self.scene.group_transform(id)
+    }
+
+    pub fn set_group_transform(&mut self, id: GroupId, transform: Mat3) {
+        self.changed = true;
+        self.scene.set_group_transform(id, transform);
     }
 
-    fn clear(&self) {
-        self.context.set_fill_style(&self.clear_color);
-        self.context.fill_rect(0.0, 0.0, self.canvas_size.0, self.canvas_size.1);
+    pub fn world_transform(&self, id: GroupId) -> Mat3 {
+        self.scene.world_transform(id)
+    }
+
+    // Moves the primitive into the group, where its coordinates are in the
+    // space of the group
+    pub fn set_group(&mut self, handle: PrimitiveHandle, group: Option<GroupId>) {
+        self.changed = true;
+        self.scene.set_group(handle, group);
+    }
+
+    pub fn remove_primitive(&mut self, handle: PrimitiveHandle) -> Option<Primitive> {
+        self.changed = true;
+        self.scene.remove(handle)
+    }
+
+    pub fn get(&self, handle: PrimitiveHandle) -> Option<&Primitive> {
+        self.scene.get(handle)
+    }
+
+    // The primitive is assumed to change
+    pub fn get_mut(&mut self, handle: PrimitiveHandle) -> Option<&mut Primitive> {
+        self.changed = true;
+        self.scene.get_mut(handle)
+    }
+
+    pub fn set_visible(&mut self, handle: PrimitiveHandle, visible: bool) {
+        self.changed = true;
+        self.scene.set_visible(handle, visible);
+    }
+
+    pub fn set_z_index(&mut self, handle: PrimitiveHandle, z_index: i32) {
+        self.changed = true;
+        self.scene.set_z_index(handle, z_index);
+    }
+
+    // Visible primitives under a canvas position, topmost first
+    pub fn hit_test(&mut self, canvas_point: &Vec3, tolerance_px: f64) -> Vec<PrimitiveHandle> {
+        self.scene.update();
+
+        let mut hits = Vec::new();
+
+        for layer in self.scene.layers_in_order().into_iter().rev() {
+            let projection = layer_projection(&self.scene, layer, &self.projection);
+            let projection = projection.as_ref().unwrap_or(&self.projection);
+
+            let point = projection.unproject_from_canvas(canvas_point);
+            let pixel = 1.0 / projection.transform().linear_scale();
+            let area = Bounds::around(&point, (tolerance_px + self.scene.screen_reach()) * pixel);
+
+            for (handle, primitive) in self.scene.iter_within(layer, &area).rev() {
+                let hit = match self.scene.group_of(handle) {
+                    Some(group) => {
+                        let local = projection.local(&self.scene.world_transform(group));
+                        let point = local.unproject_from_canvas(canvas_point);
+
+                        hit::hit(primitive, &point, tolerance_px, 1.0 / local.transform().linear_scale())
+                    }
+                    None => hit::hit(primitive, &point, tolerance_px, pixel),
+                };
+
+                if hit {
+                    hits.push(handle);
+                }
+            }
+        }
+
+        hits
     }
 
     pub fn project_to_canvas(&self, point: &Vec3) -> Vec3 {
-        &self.transform * point
+        self.projection.project_to_canvas(point)
     }
 
     pub fn unproject_from_canvas(&self, point: &Vec3) -> Vec3 {
-        &self.inverse_transform * point
+        self.projection.unproject_from_canvas(point)
+    }
+
+    pub fn viewport(&self) -> [Vec3; 4] {
+        self.projection.viewport()
     }
+}
+
+fn draw<T: Backend>(
+    scene: &Scene,
+    frame: &Frame,
+    projection: &Projection,
+    clear_color: &Color,
+    backend: &mut T,
+) {
+    backend.clear(clear_color);
+
+    let mut path = Path::new();
+
+    for id in scene.layers_in_order() {
+        let local = layer_projection(scene, id, projection);
+        let local = local.as_ref().unwrap_or(projection);
 
-    fn render_point(&self, origin: &Vec3) {
-        let ctx: &CanvasRenderingContext2d = &self.context;
+        // Immediate primitives go after retained ones of the same z_index
+        let mut immediate: Vec<&Primitive> = frame.primitives
+            .iter()
+            .filter(|(layer, _)| *layer == id)
+            .map(|(_, primitive)| primitive)
+            .collect();
 
-        let target = self.project_to_canvas(origin);
+        immediate.sort_by_key(|primitive| primitive.z_index);
 
-        ctx.begin_path();
-        ctx.arc(target.x, target.y, 3.0, 0.0, std::f64::consts::PI * 2.0)
-            .unwrap();
-        ctx.fill();
+        match id.and_then(|id| scene.layer(id)) {
+            Some(layer) => {
+                backend.begin_layer(&layer.name, layer.opacity);
+                draw_layer(scene, id, &immediate, local, backend, &mut path);
+                backend.end_layer();
+            }
+            None => draw_layer(scene, None, &immediate, local, backend, &mut path),
+        }
     }
+}
 
-    fn line(&self, from: &Vec3, to: &Vec3) {
-        let ctx: &CanvasRenderingContext2d = &self.context;
+// Projection for the primitives of a layer, when it has a transform of its own
+fn layer_projection(scene: &Scene, id: Option<LayerId>, projection: &Projection) -> Option<Projection> {
+    let transform = scene.layer(id?)?.transform.as_ref()?;
+    Some(projection.local(transform))
+}
 
-        let actual_from = self.project_to_canvas(from);
-        let actual_to = self.project_to_canvas(to);
+fn draw_layer<T: Backend>(
+    scene: &Scene,
+    layer: Option<LayerId>,
+    immediate: &[&Primitive],
+    projection: &Projection,
+    backend: &mut T,
+    path: &mut Path,
+) {
+    let scale = projection.transform().linear_scale();
 
-        ctx.move_to(actual_from.x, actual_from.y);
-        ctx.line_to(actual_to.x, actual_to.y);
+    // Anything that can reach into the canvas, with a pixel to spare for anti-aliasing
+    let visible = Bounds::from_points(&projection.viewport())
+        .map(|viewport| viewport.expand((scene.screen_reach() + 1.0) / scale))
+        .unwrap_or(Bounds {
+            min_x: f64::NEG_INFINITY,
+            min_y: f64::NEG_INFINITY,
+            max_x: f64::INFINITY,
+            max_y: f64::INFINITY,
+        });
+
+    let mut immediate = immediate
+        .iter()
+        .filter(|primitive| {
+            let reach = primitive.screen_reach() / scale;
+            primitive.bounds().is_none_or(|bounds| bounds.expand(reach).intersects(&visible))
+        })
+        .peekable();
+
+    // Projection of the group the previous primitive was in
+    let mut grouped: Option<(GroupId, Projection)> = None;
+
+    for (handle, primitive) in scene.iter_within(layer, &visible) {
+        while let Some(drawn) = immediate.next_if(|drawn| drawn.z_index < primitive.z_index) {
+            draw_primitive(backend, projection, drawn, path);
+        }
+
+        let projection = match scene.group_of(handle) {
+            Some(group) => {
+                if grouped.as_ref().is_none_or(|(previous, _)| *previous != group) {
+                    grouped = Some((group, projection.local(&scene.world_transform(group))));
+                }
+
+                &grouped.as_ref().unwrap().1
+            }
+            None => projection,
+        };
+
+        draw_primitive(backend, projection, primitive, path);
     }
 
-    fn render_segment(&self, from: &Vec3, to: &Vec3) {
-        let ctx: &CanvasRenderingContext2d = &self.context;
+    for drawn in immediate {
+        draw_primitive(backend, projection, drawn, path);
+    }
+}
 
-        let actual_from = self.project_to_canvas(from);
-        let actual_to = self.project_to_canvas(to);
+fn draw_primitive<B: Backend>(
+    backend: &mut B,
+    projection: &Projection,
+    primitive: &Primitive,
+    path: &mut Path,
+) {
+    let scale = projection.transform().linear_scale();
+
+    if let Shape::Text(text) = &primitive.shape {
+        if let Some(ink) = primitive.ink() {
+            backend.set_fill_style(&ink);
+            backend.fill_text(text, &projection.text_placement(text));
+        }
 
-        ctx.begin_path();
-        ctx.move_to(actual_from.x, actual_from.y);
-        ctx.line_to(actual_to.x, actual_to.y);
-        ctx.stroke();
+        return;
     }
 
-    fn render_grid(&self, step: f64) {
-        let ctx: &CanvasRenderingContext2d = &self.context;
+    if let Shape::Grid(grid) = &primitive.shape {
+        draw_grid(backend, projection, primitive, grid, scale);
+        return;
+    }
 
-        let o = self.unproject_from_canvas(&Vec3 {
-            x: self.canvas_size.0 / 2.0, y: self.canvas_size.1 / 2.0, z: 1.0
-        }).align(step);
+    path.clear();
+    projection.trace(&primitive.shape, path);
 
-        const OVERSCAN_FACTOR: f64 = 2.0;
-        let r = f64::sqrt(o.x * o.x + o.y * o.y);
-        let steps = (r * OVERSCAN_FACTOR / step).floor() as i32;
+    if !path.is_empty() {
+        paint(backend, projection, primitive, path, scale);
+    }
+}
 
-        ctx.begin_path();
+// Minor lines, major lines and axes are stroked in that order, each in its own color
+fn draw_grid<B: Backend>(
+    backend: &mut B,
+    projection: &Projection,
+    primitive: &Primitive,
+    grid: &Grid,
+    transform_scale: f64,
+) {
+    let stroke = match primitive.stroke {
+        Some(stroke) => stroke,
+        None => return,
+    };
 
-        let l = step * (steps as f64) * 2.0;
+    let paths = projection.trace_grid(grid);
+    let minor = grid.minor_color.unwrap_or(stroke);
+    let style = primitive.style.to_screen(transform_scale);
 
-        for i in -steps..steps {
-            let t = (i as f64) * step;
-            self.line(
-                &Vec3{ x: o.x + t, y: o.y - l, z: 1.0 },
-                &Vec3{ x: o.x + t, y: o.y + l, z: 1.0 },
-            );
+    let parts = [
+        (&paths.minor, minor.with_alpha(minor.a * paths.minor_alpha)),
+        (&paths.major, stroke),
+        (&paths.axes, grid.axis_color.unwrap_or(stroke)),
+    ];
 
-            self.line(
-                &Vec3{ x: o.x + l, y: o.y + t, z: 1.0 },
-                &Vec3{ x: o.x - l, y: o.y + t, z: 1.0 },
-            );
+    for (path, color) in parts {
+        if !path.is_empty() {
+            backend.set_stroke_style(&color, &style);
+            backend.stroke(path);
         }
+    }
+}
+
+fn paint<B: Backend>(
+    backend: &mut B,
+    projection: &Projection,
+    primitive: &Primitive,
+    path: &Path,
+    transform_scale: f64,
+) {
+    if let Shape::Point(point) = &primitive.shape {
+        if let Some(ink) = primitive.ink() {
+            backend.set_fill_style(&ink);
+            backend.fill_point(&projection.project_to_canvas(point), path);
+        }
+
+        return;
+    }
+
+    if let Some(fill) = primitive.fill.filter(|_| primitive.shape.is_closed()) {
+        backend.set_fill_style(&fill.color);
+        backend.fill(path, fill.rule);
+    }
+
+    if let Some(stroke) = primitive.stroke {
+        backend.set_stroke_style(&stroke, &primitive.style.to_screen(transform_scale));
+        backend.stroke(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::backend::recording::{DrawCommand, RecordingBackend};
+    use super::path::PathCommand;
+    use super::test_support::{plotter, primitive};
+
+    fn strokes(plotter: &Plotter<RecordingBackend>) -> Vec<&Path> {
+        plotter.backend()
+            .last_frame()
+            .iter()
+            .filter_map(|command| match command {
+                DrawCommand::Stroke(path) => Some(path),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_render_transformed_segment() {
+        let mut plotter = plotter();
+
+        plotter.set_transform(Mat3::identity().translate(&vec3!(10.0, 20.0, 1.0)));
+        plotter.add_primitive(primitive(Shape::Segment(vec3!(0.0, 0.0, 1.0), vec3!(5.0, 5.0, 1.0))));
+        plotter.render();
+
+        assert_eq!(plotter.backend().last_frame()[0], DrawCommand::Clear(Color::rgb(1.0, 1.0, 1.0)));
+        assert_eq!(strokes(&plotter)[0].commands, vec![
+            PathCommand::MoveTo(10.0, 20.0),
+            PathCommand::LineTo(15.0, 25.0),
+        ]);
+    }
+
+    #[test]
+    fn test_render_ray_clipped_to_canvas() {
+        let mut plotter = plotter();
+
+        plotter.add_primitive(primitive(Shape::Ray(vec3!(50.0, 50.0, 1.0), vec3!(1.0, 0.0, 0.0))));
+        plotter.add_primitive(primitive(Shape::Ray(vec3!(150.0, 50.0, 1.0), vec3!(1.0, 0.0, 0.0))));
+        plotter.render();
+
+        let strokes = strokes(&plotter);
+
+        assert_eq!(strokes.len(), 1);
+        assert_eq!(strokes[0].commands, vec![
+            PathCommand::MoveTo(50.0, 50.0),
+            PathCommand::LineTo(100.0, 50.0),
+        ]);
+    }
+
+    #[test]
+    fn test_render_world_line_width() {
+        let mut plotter = plotter();
+
+        plotter.set_transform(Mat3::identity().scale(&vec3!(2.0, 2.0, 1.0)));
+        plotter.add_primitive(Primitive {
+            style: StrokeStyle {
+                dash: vec![1.0, 2.0],
+                ..StrokeStyle::with_width(LineWidth::World(1.5))
+            },
+            ..primitive(Shape::Circle { center: vec3!(10.0, 10.0, 1.0), radius: 5.0 })
+        });
+        plotter.render();
+
+        let style = plotter.backend().last_frame().iter().find_map(|command| match command {
+            DrawCommand::SetStrokeStyle(_, style) => Some(style),
+            _ => None,
+        });
+
+        assert_eq!(style.map(|style| style.width), Some(LineWidth::Screen(3.0)));
+        assert_eq!(style.map(|style| style.dash.clone()), Some(vec![2.0, 4.0]));
+    }
+
+    #[test]
+    fn test_render_filled_polygon() {
+        let mut plotter = plotter();
+
+        let fill = Fill { color: Color::rgb(1.0, 0.0, 0.0), rule: FillRule::EvenOdd };
+
+        plotter.add_primitive(Primitive {
+            fill: Some(fill),
+            ..primitive(Shape::Polygon(vec![
+                vec3!(0.0, 0.0, 1.0),
+                vec3!(10.0, 0.0, 1.0),
+                vec3!(0.0, 10.0, 1.0),
+            ]))
+        });
+        plotter.render();
+
+        let frame = plotter.backend().last_frame();
+
+        assert_eq!(frame[1], DrawCommand::SetFillStyle(fill.color));
+        assert!(matches!(frame[2], DrawCommand::Fill(_, FillRule::EvenOdd)));
+        assert!(matches!(frame[4], DrawCommand::Stroke(_)));
+    }
+
+    #[test]
+    fn test_render_to_raster() {
+        let mut plotter = plotter();
+        let mut raster = RasterBackend::new(100, 100);
+
+        plotter.set_clear_color(&Color::rgb(0.0, 0.0, 1.0));
+        plotter.add_primitive(Primitive {
+            fill: Some(Fill { color: Color::rgb(1.0, 0.0, 0.0), rule: FillRule::NonZero }),
+            ..primitive(Shape::Circle { center: vec3!(50.0, 50.0, 1.0), radius: 20.0 })
+        });
+        plotter.render_to(&mut raster);
+
+        assert_eq!(raster.pixel(5, 5), [0, 0, 255, 255]);
+        assert_eq!(raster.pixel(50, 50), [255, 0, 0, 255]);
+        // Half of the one pixel wide outline covers the bottom row
+        let outline = raster.pixel(50, 70);
+        assert!(outline[0] == 0 && outline[2] > 64 && outline[2] < 192, "{:?}", outline);
+        assert!(plotter.export_png().starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn test_pen_paths() {
+        let mut plotter = plotter();
+
+        plotter.add_primitive(primitive(Shape::Segment(vec3!(0.0, 0.0, 1.0), vec3!(100.0, 100.0, 1.0))));
+        plotter.add_primitive(primitive(Shape::Point(vec3!(50.0, 50.0, 1.0))));
+
+        let mut options = PlotOptions {
+            page: pen::Page { width: 120.0, height: 120.0, margin: 10.0, origin: pen::PageOrigin::TopLeft },
+            ..PlotOptions::default()
+        };
+
+        let paths = plotter.pen_paths(&options);
+
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[0].points, vec![vec3!(10.0, 10.0, 1.0), vec3!(110.0, 110.0, 1.0)]);
+        assert_eq!(paths[1].points, vec![vec3!(60.0, 60.0, 1.0)]);
+
+        options.points = pen::PointMode::Skip;
+
+        assert_eq!(plotter.pen_paths(&options).len(), 1);
+    }
+
+    #[test]
+    fn test_render_layers() {
+        let mut plotter = plotter();
+
+        let ink = plotter.add_layer(Layer {
+            opacity: 0.5,
+            transform: Some(Mat3::identity().translate(&vec3!(10.0, 0.0, 1.0))),
+            ..Layer::new("ink")
+        });
+        let grid = plotter.add_layer(Layer { z_index: -1, ..Layer::new("grid") });
+
+        let segment = || primitive(Shape::Segment(vec3!(0.0, 0.0, 1.0), vec3!(0.0, 10.0, 1.0)));
+
+        let moved = plotter.add_primitive_to(ink, segment());
+        plotter.add_primitive_to(grid, segment());
+        plotter.render();
+
+        let frame = plotter.backend().last_frame();
+
+        assert_eq!(frame[1], DrawCommand::BeginLayer("grid".into(), 1.0));
+        assert_eq!(frame[4], DrawCommand::EndLayer);
+        assert_eq!(frame[5], DrawCommand::BeginLayer("ink".into(), 0.5));
+        assert_eq!(strokes(&plotter)[1].commands[0], PathCommand::MoveTo(10.0, 0.0));
+
+        // Hit testing goes through the transform of the layer
+        assert_eq!(plotter.hit_test(&vec3!(10.0, 5.0, 1.0), 1.0), vec![moved]);
+
+        plotter.layer_mut(grid).unwrap().visible = false;
+        plotter.render();
+
+        assert_eq!(strokes(&plotter).len(), 1);
+        let document = plotter.export_svg();
+        assert!(document.contains("<g id=\"ink\">\n<path d=\"M10 0L10 10\" fill=\"none\" stroke=\"rgb(0, 0, 0)\""));
+        assert!(document.contains("stroke-opacity=\"0.502\""));
+    }
+
+    #[test]
+    fn test_render_groups() {
+        let mut plotter = plotter();
+
+        let body = plotter.add_group(None, Mat3::identity().translate(&vec3!(50.0, 50.0, 1.0)));
+        let hand = plotter.add_group(Some(body), Mat3::identity().rotate(-std::f64::consts::PI / 2.0));
+
+        let segment = Shape::Segment(vec3!(0.0, 0.0, 1.0), vec3!(10.0, 0.0, 1.0));
+        let segment = plotter.add_primitive(primitive(segment));
+        plotter.set_group(segment, Some(hand));
+        plotter.render();
+
+        // A quarter turn within the group, then moved along with its parent
+        let commands = &strokes(&plotter)[0].commands;
+        assert_eq!(commands[0], PathCommand::MoveTo(50.0, 50.0));
+
+        match commands[1] {
+            PathCommand::LineTo(x, y) => assert!((x - 50.0).abs() < 1e-9 && (y - 60.0).abs() < 1e-9),
+            _ => panic!(),
+        }
+
+        assert_eq!(plotter.hit_test(&vec3!(50.0, 58.0, 1.0), 1.0), vec![segment]);
+        assert!(plotter.hit_test(&vec3!(58.0, 50.0, 1.0), 1.0).is_empty());
+
+        plotter.set_group_transform(body, Mat3::identity().translate(&vec3!(20.0, 50.0, 1.0)));
+        assert_eq!(plotter.hit_test(&vec3!(20.0, 58.0, 1.0), 1.0), vec![segment]);
+    }
+
+    #[test]
+    fn test_render_on_demand() {
+        let mut plotter = plotter();
+        let frames = |plotter: &Plotter<RecordingBackend>| {
+            plotter.backend()
+                .commands
+                .iter()
+                .filter(|command| matches!(command, DrawCommand::Clear(_)))
+                .count()
+        };
+
+        let handle = plotter.add_primitive(primitive(Shape::Point(vec3!(10.0, 10.0, 1.0))));
+        plotter.render();
+        plotter.render();
+        assert_eq!(frames(&plotter), 1);
+
+        plotter.get_mut(handle).unwrap().stroke = Some(Color::rgb(1.0, 0.0, 0.0));
+        plotter.render();
+        assert_eq!(frames(&plotter), 2);
+
+        // Setting what is already there changes nothing
+        plotter.set_transform(Mat3::identity());
+        plotter.set_clear_color(&Color::rgb(1.0, 1.0, 1.0));
+        plotter.update_canvas_size();
+        plotter.render();
+        assert_eq!(frames(&plotter), 2);
+
+        plotter.set_transform(Mat3::identity().translate(&vec3!(5.0, 0.0, 1.0)));
+        plotter.render();
+        plotter.request_redraw();
+        plotter.render();
+        assert_eq!(frames(&plotter), 4);
+
+        // Immediate primitives take one more frame to clear
+        plotter.draw().point(vec3!(20.0, 20.0, 1.0));
+        plotter.render();
+        assert!(plotter.needs_redraw());
+        plotter.render();
+        plotter.render();
+        assert_eq!(frames(&plotter), 6);
+        assert!(!plotter.needs_redraw());
+    }
+
+    #[test]
+    fn test_immediate_primitives() {
+        let mut plotter = plotter();
+        let red = Color::rgb(1.0, 0.0, 0.0);
+
+        plotter.add_primitive(Primitive { z_index: 1, ..primitive(Shape::Point(vec3!(10.0, 10.0, 1.0))) });
+        plotter.add_primitive(primitive(Shape::Point(vec3!(20.0, 20.0, 1.0))));
+
+        plotter.draw().segment(vec3!(0.0, 0.0, 1.0), vec3!(10.0, 0.0, 1.0)).color(red).z_index(1);
+        plotter.draw().circle(vec3!(0.0, 0.0, 1.0), 1.0).z_index(-1);
+        plotter.render();
+
+        let fills = |plotter: &Plotter<RecordingBackend>| -> Vec<Color> {
+            plotter.backend()
+                .last_frame()
+                .iter()
+                .filter_map(|command| match command {
+                    DrawCommand::SetFillStyle(color) | DrawCommand::SetStrokeStyle(color, _) => Some(*color),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        // Merged with the retained points, after them within the same z_index
+        let black = Color::rgb(0.0, 0.0, 0.0);
+        assert_eq!(fills(&plotter), vec![black, black, black, red]);
+
+        // Still there for an export of the frame that was shown
+        assert!(plotter.export_svg().contains("rgb(255, 0, 0)"));
+
+        plotter.render();
+        assert_eq!(fills(&plotter), vec![black, black]);
+    }
+
+    #[test]
+    fn test_pen_per_layer() {
+        let mut plotter = plotter();
+
+        let first = plotter.add_layer(Layer::new("first"));
+        let second = plotter.add_layer(Layer::new("second"));
+
+        let segment = |y: f64| primitive(Shape::Segment(vec3!(0.0, y, 1.0), vec3!(10.0, y, 1.0)));
+
+        plotter.add_primitive_to(first, segment(0.0));
+        plotter.add_primitive_to(second, segment(5.0));
+
+        let options = PlotOptions { optimize: None, ..PlotOptions::default() };
+        assert_eq!(pen::pens(&plotter.pen_paths(&options)).len(), 2);
+
+        // The same color is one pen when pens go by color
+        let options = PlotOptions { layer_pens: false, ..options };
+        assert_eq!(pen::pens(&plotter.pen_paths(&options)).len(), 1);
+    }
+
+    #[test]
+    fn test_hit_test() {
+        let mut plotter = plotter();
+
+        plotter.set_transform(Mat3::identity().translate(&vec3!(50.0, 50.0, 1.0)).scale(&vec3!(2.0, 2.0, 1.0)));
+
+        let below = plotter.add_primitive(Primitive {
+            z_index: 1,
+            ..primitive(Shape::Segment(vec3!(-10.0, 0.0, 1.0), vec3!(10.0, 0.0, 1.0)))
+        });
+        let above = plotter.add_primitive(Primitive {
+            z_index: 2,
+            ..primitive(Shape::Point(vec3!(5.0, 0.0, 1.0)))
+        });
+        let hidden = plotter.add_primitive(primitive(Shape::Point(vec3!(5.0, 0.0, 1.0))));
+        plotter.set_visible(hidden, false);
+
+        assert_eq!(plotter.hit_test(&vec3!(60.0, 51.0, 1.0), 2.0), vec![above, below]);
+        assert_eq!(plotter.hit_test(&vec3!(40.0, 51.0, 1.0), 2.0), vec![below]);
+        assert_eq!(plotter.hit_test(&vec3!(40.0, 60.0, 1.0), 2.0), vec![]);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut plotter = plotter();
+        plotter.resize(300.0, 200.0, 2.0);
+
+        assert_eq!(plotter.canvas_size(), (300.0, 200.0));
+        assert_eq!(plotter.backend().size(), (300.0, 200.0));
+        assert_eq!(plotter.pixel_ratio(), 2.0);
+
+        // Sketches keep working in CSS pixels
+        let viewport = plotter.viewport();
+        assert_eq!((viewport[2].x, viewport[2].y), (300.0, 200.0));
+
+        plotter.resize(300.0, 200.0, 0.0);
+        assert_eq!(plotter.pixel_ratio(), 1.0);
+    }
+
+    #[test]
+    fn test_render_culls_offscreen_primitives() {
+        let mut plotter = plotter();
+
+        for i in 0..1000 {
+            let x = (i as f64) * 10.0;
+            plotter.add_primitive(primitive(Shape::Segment(vec3!(x, 0.0, 1.0), vec3!(x + 5.0, 50.0, 1.0))));
+        }
+
+        // Including the one that starts right at the edge
+        plotter.render();
+        assert_eq!(strokes(&plotter).len(), 11);
+
+        plotter.set_transform(Mat3::identity().scale(&vec3!(0.01, 0.01, 1.0)));
+        plotter.render();
+        assert_eq!(strokes(&plotter).len(), 1000);
+    }
+
+    #[test]
+    fn test_render_grid() {
+        let mut plotter = plotter();
+
+        let grid = plotter.add_primitive(primitive(Shape::Grid(Grid::new(10.0))));
+        plotter.set_transform(Mat3::identity().translate(&vec3!(-100.0, -100.0, 1.0)));
+        plotter.render();
+
+        let lines = strokes(&plotter)[0]
+            .commands
+            .iter()
+            .filter(|command| matches!(command, PathCommand::MoveTo(_, _)))
+            .count();
+
+        assert!(lines > 0);
+
+        plotter.set_visible(grid, false);
+        plotter.render();
 
-        ctx.stroke();
+        assert!(strokes(&plotter).is_empty());
     }
 }
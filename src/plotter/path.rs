@@ -0,0 +1,217 @@
+use std::f64::consts::PI;
+
+use crate::math::bezier::{sample_cubic_bezier, sample_quadratic_bezier};
+use crate::math::ellipse::EllipticalArc;
+use crate::math::vec3::Vec3;
+use crate::vec3;
+
+// Path segments in canvas coordinates, following the canvas path API
+#[derive(Clone, PartialEq, Debug)]
+pub enum PathCommand {
+    MoveTo(f64, f64),
+    LineTo(f64, f64),
+    QuadraticCurveTo(f64, f64, f64, f64),
+    BezierCurveTo(f64, f64, f64, f64, f64, f64),
+    // Connected to the current point with a straight line, like CanvasRenderingContext2d::ellipse
+    Ellipse(EllipticalArc),
+    ClosePath,
+}
+
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Path {
+    pub commands: Vec<PathCommand>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Path { commands: Vec::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn move_to(&mut self, x: f64, y: f64) {
+        self.commands.push(PathCommand::MoveTo(x, y));
+    }
+
+    pub fn line_to(&mut self, x: f64, y: f64) {
+        self.commands.push(PathCommand::LineTo(x, y));
+    }
+
+    pub fn quadratic_curve_to(&mut self, cpx: f64, cpy: f64, x: f64, y: f64) {
+        self.commands.push(PathCommand::QuadraticCurveTo(cpx, cpy, x, y));
+    }
+
+    pub fn bezier_curve_to(&mut self, cp1x: f64, cp1y: f64, cp2x: f64, cp2y: f64, x: f64, y: f64) {
+        self.commands.push(PathCommand::BezierCurveTo(cp1x, cp1y, cp2x, cp2y, x, y));
+    }
+
+    pub fn ellipse(&mut self, arc: EllipticalArc) {
+        self.commands.push(PathCommand::Ellipse(arc));
+    }
+
+    pub fn close_path(&mut self) {
+        self.commands.push(PathCommand::ClosePath);
+    }
+}
+
+// Straight line approximation of a part of a path
+#[derive(Clone, PartialEq, Debug)]
+pub struct Subpath {
+    pub points: Vec<Vec3>,
+    pub closed: bool,
+}
+
+impl Path {
+    // Replaces curves and arcs with polylines that stay within `tolerance`
+    // of the original, in canvas pixels
+    pub fn flatten(&self, tolerance: f64) -> Vec<Subpath> {
+        let mut subpaths: Vec<Subpath> = Vec::new();
+        let mut current: Vec<Vec3> = Vec::new();
+
+        fn finish(subpaths: &mut Vec<Subpath>, current: &mut Vec<Vec3>, closed: bool) {
+            if current.len() > 1 {
+                subpaths.push(Subpath { points: current.clone(), closed });
+            }
+
+            if closed {
+                // The canvas starts the next subpath where the closed one began
+                let start = current.first().copied();
+                current.clear();
+                current.extend(start);
+            } else {
+                current.clear();
+            }
+        }
+
+        for command in &self.commands {
+            match *command {
+                PathCommand::MoveTo(x, y) => {
+                    finish(&mut subpaths, &mut current, false);
+                    current.push(vec3!(x, y, 1.0));
+                }
+                PathCommand::LineTo(x, y) => {
+                    current.push(vec3!(x, y, 1.0));
+                }
+                PathCommand::QuadraticCurveTo(cpx, cpy, x, y) => {
+                    let from = current.last().copied().unwrap_or(vec3!(cpx, cpy, 1.0));
+                    let control = vec3!(cpx, cpy, 1.0);
+                    let to = vec3!(x, y, 1.0);
+
+                    let ddx = from.x - 2.0 * cpx + x;
+                    let ddy = from.y - 2.0 * cpy + y;
+                    let segments = curve_segments(f64::hypot(ddx, ddy) / 4.0, tolerance);
+
+                    let points = sample_quadratic_bezier(&from, &control, &to, segments);
+                    current.extend_from_slice(&points[1..]);
+                }
+                PathCommand::BezierCurveTo(cp1x, cp1y, cp2x, cp2y, x, y) => {
+                    let from = current.last().copied().unwrap_or(vec3!(cp1x, cp1y, 1.0));
+                    let control1 = vec3!(cp1x, cp1y, 1.0);
+                    let control2 = vec3!(cp2x, cp2y, 1.0);
+                    let to = vec3!(x, y, 1.0);
+
+                    let dd1 = f64::hypot(from.x - 2.0 * cp1x + cp2x, from.y - 2.0 * cp1y + cp2y);
+                    let dd2 = f64::hypot(cp1x - 2.0 * cp2x + x, cp1y - 2.0 * cp2y + y);
+                    let segments = curve_segments(f64::max(dd1, dd2) * 3.0 / 4.0, tolerance);
+
+                    let points = sample_cubic_bezier(&from, &control1, &control2, &to, segments);
+                    current.extend_from_slice(&points[1..]);
+                }
+                PathCommand::Ellipse(arc) => {
+                    let sweep = arc.sweep();
+                    let radius = f64::max(arc.rx, arc.ry);
+
+                    // Largest step for which the chord stays within tolerance
+                    let step = if radius > tolerance {
+                        2.0 * f64::acos(1.0 - tolerance / radius)
+                    } else {
+                        PI / 2.0
+                    };
+
+                    let segments = ((sweep.abs() / step).ceil() as usize).clamp(1, 10_000);
+
+                    for i in 0..=segments {
+                        let t = arc.start + sweep * (i as f64) / (segments as f64);
+                        current.push(arc.point_at(t));
+                    }
+                }
+                PathCommand::ClosePath => {
+                    finish(&mut subpaths, &mut current, true);
+                }
+            }
+        }
+
+        finish(&mut subpaths, &mut current, false);
+
+        subpaths
+    }
+}
+
+// Number of segments for a curve whose points are at most `deviation` away
+// from a straight line, so that every segment is within the tolerance
+fn curve_segments(deviation: f64, tolerance: f64) -> usize {
+    (f64::sqrt(deviation / tolerance).ceil() as usize).clamp(1, 10_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten_lines() {
+        let mut path = Path::new();
+
+        path.move_to(0.0, 0.0);
+        path.line_to(10.0, 0.0);
+        path.line_to(10.0, 10.0);
+        path.close_path();
+        path.line_to(0.0, 10.0);
+        path.move_to(5.0, 5.0);
+
+        let subpaths = path.flatten(0.25);
+
+        assert_eq!(subpaths, vec![
+            Subpath {
+                points: vec![vec3!(0.0, 0.0, 1.0), vec3!(10.0, 0.0, 1.0), vec3!(10.0, 10.0, 1.0)],
+                closed: true,
+            },
+            Subpath {
+                points: vec![vec3!(0.0, 0.0, 1.0), vec3!(0.0, 10.0, 1.0)],
+                closed: false,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_flatten_within_tolerance() {
+        let mut path = Path::new();
+
+        path.ellipse(EllipticalArc::full(vec3!(0.0, 0.0, 1.0), 100.0, 100.0, 0.0));
+        path.move_to(0.0, 0.0);
+        path.quadratic_curve_to(50.0, 100.0, 100.0, 0.0);
+
+        let subpaths = path.flatten(0.5);
+
+        for point in &subpaths[0].points {
+            assert!(f64::abs(f64::hypot(point.x, point.y) - 100.0) < 1e-9);
+        }
+
+        // Midpoints of the chords can't be too far from the circle either
+        for pair in subpaths[0].points.windows(2) {
+            let middle = vec3!((pair[0].x + pair[1].x) / 2.0, (pair[0].y + pair[1].y) / 2.0, 1.0);
+            assert!(100.0 - f64::hypot(middle.x, middle.y) <= 0.5);
+        }
+
+        let curve = &subpaths[1].points;
+
+        assert_eq!(curve.first(), Some(&vec3!(0.0, 0.0, 1.0)));
+        assert_eq!(curve.last(), Some(&vec3!(100.0, 0.0, 1.0)));
+        assert!(curve.len() > 5);
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt::Write;
+
+use crate::common::format::number;
+
+use super::{pen_number, pens, PenPath, PlotOptions};
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct GcodeOptions {
+    pub plot: PlotOptions,
+    // Written as they are, so they may span several lines
+    pub pen_up: String,
+    pub pen_down: String,
+    // Issued with the pen up whenever the next path has another color.
+    // Empty to keep going with the same pen.
+    pub pen_change: String,
+    // Millimeters per minute
+    pub draw_feed: f64,
+    pub travel_feed: f64,
+}
+
+impl Default for GcodeOptions {
+    fn default() -> Self {
+        GcodeOptions {
+            plot: PlotOptions::default(),
+            pen_up: "G0 Z5".into(),
+            pen_down: "G0 Z0".into(),
+            pen_change: "M0".into(),
+            draw_feed: 1500.0,
+            travel_feed: 3000.0,
+        }
+    }
+}
+
+pub fn export(paths: &[PenPath], options: &GcodeOptions) -> String {
+    let pens = pens(paths);
+    let mut gcode = String::new();
+    let mut current_pen = None;
+
+    writeln!(gcode, "G21 ; millimeters").unwrap();
+    writeln!(gcode, "G90 ; absolute coordinates").unwrap();
+    writeln!(gcode, "{}", options.pen_up).unwrap();
+
+    for path in paths {
+        let pen = pen_number(&pens, path);
+
+        if current_pen != pen {
+            let number = pen.unwrap();
+
+            match &pens[number - 1].layer {
+                Some(layer) => writeln!(gcode, "; pen {}: {}", number, layer).unwrap(),
+                None => writeln!(gcode, "; pen {}: {}", number, path.color.as_css_string()).unwrap(),
+            }
+
+            if current_pen.is_some() && !options.pen_change.is_empty() {
+                writeln!(gcode, "{}", options.pen_change).unwrap();
+            }
+
+            current_pen = pen;
+        }
+
+        let start = &path.points[0];
+        writeln!(gcode, "G1 X{} Y{} F{}", number(start.x), number(start.y), number(options.travel_feed)).unwrap();
+        writeln!(gcode, "{}", options.pen_down).unwrap();
+
+        for (i, point) in path.points[1..].iter().enumerate() {
+            if i == 0 {
+                writeln!(gcode, "G1 X{} Y{} F{}", number(point.x), number(point.y), number(options.draw_feed)).unwrap();
+            } else {
+                writeln!(gcode, "G1 X{} Y{}", number(point.x), number(point.y)).unwrap();
+            }
+        }
+
+        writeln!(gcode, "{}", options.pen_up).unwrap();
+    }
+
+    gcode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::colors::Color;
+    use crate::math::vec3::Vec3;
+    use crate::vec3;
+
+    #[test]
+    fn test_export() {
+        let options = GcodeOptions {
+            pen_up: "M3 S0".into(),
+            pen_down: "M3 S1000".into(),
+            ..GcodeOptions::default()
+        };
+
+        let paths = vec![
+            PenPath { color: Color::rgb(0.0, 0.0, 0.0), layer: None, points: vec![vec3!(1.0, 2.0, 1.0), vec3!(3.5, 4.0, 1.0), vec3!(5.0, 4.0, 1.0)] },
+            PenPath { color: Color::rgb(1.0, 0.0, 0.0), layer: None, points: vec![vec3!(0.25, 0.5, 1.0)] },
+        ];
+
+        assert_eq!(export(&paths, &options), concat!(
+            "G21 ; millimeters\n",
+            "G90 ; absolute coordinates\n",
+            "M3 S0\n",
+            "; pen 1: rgb(0, 0, 0)\n",
+            "G1 X1 Y2 F3000\n",
+            "M3 S1000\n",
+            "G1 X3.5 Y4 F1500\n",
+            "G1 X5 Y4\n",
+            "M3 S0\n",
+            "; pen 2: rgb(255, 0, 0)\n",
+            "M0\n",
+            "G1 X0.25 Y0.5 F3000\n",
+            "M3 S1000\n",
+            "M3 S0\n",
+        ));
+    }
+}
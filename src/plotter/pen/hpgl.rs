@@ -0,0 +1,79 @@
+use std::fmt::Write;
+
+use super::{pen_number, pens, PenPath};
+
+// HP-GL plotter units per millimeter
+const UNITS_PER_MM: f64 = 40.0;
+
+// Every pen is numbered in order of appearance
+pub fn export(paths: &[PenPath]) -> String {
+    let pens = pens(paths);
+    let mut hpgl = String::from("IN;\n");
+    let mut current_pen = 0;
+
+    for path in paths {
+        let pen = pen_number(&pens, path).unwrap();
+
+        if pen != current_pen {
+            writeln!(hpgl, "SP{};", pen).unwrap();
+            current_pen = pen;
+        }
+
+        let start = &path.points[0];
+        writeln!(hpgl, "PU{},{};", units(start.x), units(start.y)).unwrap();
+
+        if path.is_dot() {
+            hpgl.push_str("PD;\n");
+            continue;
+        }
+
+        let coordinates: Vec<String> = path.points[1..]
+            .iter()
+            .map(|point| format!("{},{}", units(point.x), units(point.y)))
+            .collect();
+
+        writeln!(hpgl, "PD{};", coordinates.join(",")).unwrap();
+    }
+
+    hpgl.push_str("PU;\nSP0;\n");
+    hpgl
+}
+
+fn units(millimeters: f64) -> i64 {
+    (millimeters * UNITS_PER_MM).round() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::colors::Color;
+    use crate::math::vec3::Vec3;
+    use crate::vec3;
+
+    #[test]
+    fn test_export() {
+        let black = Color::rgb(0.0, 0.0, 0.0);
+        let red = Color::rgb(1.0, 0.0, 0.0);
+
+        let paths = vec![
+            PenPath { color: black, layer: None, points: vec![vec3!(1.0, 2.0, 1.0), vec3!(3.0, 4.0, 1.0), vec3!(5.0, 4.0, 1.0)] },
+            PenPath { color: red, layer: None, points: vec![vec3!(0.5, 0.5, 1.0)] },
+            PenPath { color: black, layer: None, points: vec![vec3!(0.0, 0.0, 1.0), vec3!(0.01, 0.0, 1.0)] },
+        ];
+
+        assert_eq!(export(&paths), concat!(
+            "IN;\n",
+            "SP1;\n",
+            "PU40,80;\n",
+            "PD120,160,200,160;\n",
+            "SP2;\n",
+            "PU20,20;\n",
+            "PD;\n",
+            "SP1;\n",
+            "PU0,0;\n",
+            "PD0,0;\n",
+            "PU;\n",
+            "SP0;\n",
+        ));
+    }
+}
@@ -0,0 +1,172 @@
+use crate::common::colors::Color;
+use crate::math::mat3::Mat3;
+use crate::math::vec3::Vec3;
+use crate::vec3;
+
+use optimize::{OptimizeOptions, TravelReport};
+
+pub mod gcode;
+pub mod hpgl;
+pub mod optimize;
+
+// One continuous pen down stroke, in page millimeters. A single point is a dot.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PenPath {
+    pub color: Color,
+    // Layer the path was drawn on, when every layer has a pen of its own
+    pub layer: Option<String>,
+    pub points: Vec<Vec3>,
+}
+
+impl PenPath {
+    pub fn is_dot(&self) -> bool {
+        self.points.len() == 1
+    }
+}
+
+// Physical pen, which draws either one color or one whole layer. Its color
+// is the one of the first path it draws.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Pen {
+    pub color: Color,
+    pub layer: Option<String>,
+}
+
+impl Pen {
+    pub fn draws(&self, path: &PenPath) -> bool {
+        match (&self.layer, &path.layer) {
+            (Some(layer), Some(other)) => layer == other,
+            (None, None) => self.color.to_rgba8() == path.color.to_rgba8(),
+            _ => false,
+        }
+    }
+}
+
+// Distinct pens in order of first appearance
+pub fn pens(paths: &[PenPath]) -> Vec<Pen> {
+    let mut pens: Vec<Pen> = Vec::new();
+
+    for path in paths {
+        if pen_number(&pens, path).is_none() {
+            pens.push(Pen { color: path.color, layer: path.layer.clone() });
+        }
+    }
+
+    pens
+}
+
+// Pens are numbered from one, like plotter pen slots
+pub fn pen_number(pens: &[Pen], path: &PenPath) -> Option<usize> {
+    pens.iter()
+        .position(|pen| pen.draws(path))
+        .map(|index| index + 1)
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PageOrigin {
+    TopLeft,
+    // Usual for plotters, where y grows upwards
+    BottomLeft,
+}
+
+// Physical sheet the canvas is fitted onto, keeping its aspect ratio
+#[derive(Clone, PartialEq, Debug)]
+pub struct Page {
+    // Millimeters
+    pub width: f64,
+    pub height: f64,
+    pub margin: f64,
+    pub origin: PageOrigin,
+}
+
+impl Page {
+    pub fn a4() -> Self {
+        Page {
+            width: 210.0,
+            height: 297.0,
+            margin: 10.0,
+            origin: PageOrigin::BottomLeft,
+        }
+    }
+
+    // Maps canvas pixels onto the page, centered within the margins
+    pub fn transform(&self, canvas_size: (f64, f64)) -> Mat3 {
+        let (canvas_width, canvas_height) = canvas_size;
+        let available_width = self.width - self.margin * 2.0;
+        let available_height = self.height - self.margin * 2.0;
+
+        let scale = f64::min(available_width / canvas_width, available_height / canvas_height);
+        let scale = if scale.is_finite() { scale.max(0.0) } else { 0.0 };
+
+        let left = self.margin + (available_width - canvas_width * scale) / 2.0;
+        let top = self.margin + (available_height - canvas_height * scale) / 2.0;
+
+        match self.origin {
+            PageOrigin::TopLeft => Mat3::identity()
+                .translate(&vec3!(left, top, 1.0))
+                .scale(&vec3!(scale, scale, 1.0)),
+            PageOrigin::BottomLeft => Mat3::identity()
+                .translate(&vec3!(left, self.height - top, 1.0))
+                .scale(&vec3!(scale, -scale, 1.0)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PointMode {
+    // Pen goes down and right back up
+    Dot,
+    Skip,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PlotOptions {
+    pub page: Page,
+    // Largest distance between a curve and the lines it is plotted with, in millimeters
+    pub tolerance: f64,
+    pub points: PointMode,
+    // Reordering of the paths to save pen travel, or None to keep the drawing order
+    pub optimize: Option<OptimizeOptions>,
+    // One pen per layer rather than per color. Primitives on no layer
+    // still get a pen for each of their colors.
+    pub layer_pens: bool,
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        PlotOptions {
+            page: Page::a4(),
+            tolerance: 0.05,
+            points: PointMode::Dot,
+            optimize: Some(OptimizeOptions::default()),
+            layer_pens: true,
+        }
+    }
+}
+
+// Everything a pen plotter draws, in the order it draws it
+#[derive(Clone, PartialEq, Debug)]
+pub struct Plot {
+    pub paths: Vec<PenPath>,
+    pub travel: TravelReport,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_transform() {
+        let page = Page { width: 100.0, height: 80.0, margin: 10.0, origin: PageOrigin::TopLeft };
+        let m = page.transform((200.0, 100.0));
+
+        assert_eq!(&m * &vec3!(0.0, 0.0, 1.0), vec3!(10.0, 20.0, 1.0));
+        assert_eq!(&m * &vec3!(200.0, 100.0, 1.0), vec3!(90.0, 60.0, 1.0));
+
+        let page = Page { origin: PageOrigin::BottomLeft, ..page };
+        let m = page.transform((200.0, 100.0));
+
+        assert_eq!(&m * &vec3!(0.0, 0.0, 1.0), vec3!(10.0, 60.0, 1.0));
+        assert_eq!(&m * &vec3!(200.0, 100.0, 1.0), vec3!(90.0, 20.0, 1.0));
+    }
+}
@@ -0,0 +1,548 @@
+use std::collections::HashMap;
+
+use crate::math::vec3::Vec3;
+use crate::vec3;
+
+use super::{pens, pen_number, PenPath};
+
+// Passes over the whole order before 2-opt gives up on finding improvements
+const MAX_TWO_OPT_PASSES: usize = 32;
+// Longest run of the order 2-opt tries to reverse. The greedy order rarely
+// gains from reversing longer ones.
+const TWO_OPT_WINDOW: usize = 64;
+// Above this many paths of one pen, the greedy order is kept as it is
+const MAX_TWO_OPT_PATHS: usize = 10_000;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct OptimizeOptions {
+    // Paths are allowed to be drawn from their end to their start
+    pub reverse: bool,
+    // Ends closer than this, in millimeters, are joined into a single path
+    pub merge_distance: f64,
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        OptimizeOptions {
+            reverse: true,
+            merge_distance: 0.01,
+        }
+    }
+}
+
+// Distance the pen travels in the air, in millimeters
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct TravelReport {
+    pub before: f64,
+    pub after: f64,
+}
+
+// The plotter starts and ends at the page origin
+pub fn pen_up_distance(paths: &[PenPath]) -> f64 {
+    let mut position = vec3!(0.0, 0.0, 1.0);
+    let mut distance = 0.0;
+
+    for path in paths {
+        distance += length(&position, &path.points[0]);
+        position = path.points[path.points.len() - 1];
+    }
+
+    distance + length(&position, &vec3!(0.0, 0.0, 1.0))
+}
+
+// Reorders the paths so the pen spends less time in the air. Every pen is
+// used only once, in the order it first appears.
+pub fn optimize(paths: Vec<PenPath>, options: &OptimizeOptions) -> (Vec<PenPath>, TravelReport) {
+    let before = pen_up_distance(&paths);
+    let pens = pens(&paths);
+
+    let mut groups: Vec<Vec<PenPath>> = vec![Vec::new(); pens.len()];
+
+    for path in paths {
+        let pen = pen_number(&pens, &path).unwrap();
+        groups[pen - 1].push(path);
+    }
+
+    let mut optimized = Vec::new();
+    let mut position = vec3!(0.0, 0.0, 1.0);
+
+    for group in groups {
+        let merged = merge(group, options);
+        let mut order = nearest_neighbor(&merged, &position, options.reverse);
+
+        if options.reverse && merged.len() <= MAX_TWO_OPT_PATHS {
+            two_opt(&merged, &mut order, &position);
+        }
+
+        for (index, reversed) in order {
+            let mut path = merged[index].clone();
+
+            if reversed {
+                path.points.reverse();
+            }
+
+            position = path.points[path.points.len() - 1];
+            optimized.push(path);
+        }
+    }
+
+    let after = pen_up_distance(&optimized);
+
+    (optimized, TravelReport { before, after })
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum End {
+    Start,
+    Finish,
+}
+
+// Endpoints bucketed on a grid, so that only cells around a point have to be
+// searched. Dots have a single endpoint, their start.
+struct Endpoints<'a> {
+    paths: &'a [PenPath],
+    cell: f64,
+    cells: HashMap<(i64, i64), Vec<(usize, End)>>,
+    // Keys of the outermost cells, past which searches can stop
+    min: (i64, i64),
+    max: (i64, i64),
+    count: usize,
+}
+
+impl<'a> Endpoints<'a> {
+    fn new(paths: &'a [PenPath], cell: f64) -> Self {
+        let mut endpoints = Endpoints {
+            paths,
+            cell: cell.max(1e-9),
+            cells: HashMap::new(),
+            min: (i64::MAX, i64::MAX),
+            max: (i64::MIN, i64::MIN),
+            count: 0,
+        };
+
+        for (index, path) in paths.iter().enumerate() {
+            endpoints.insert(index, End::Start);
+
+            if !path.is_dot() {
+                endpoints.insert(index, End::Finish);
+            }
+        }
+
+        endpoints
+    }
+
+    fn key(&self, point: &Vec3) -> (i64, i64) {
+        ((point.x / self.cell).floor() as i64, (point.y / self.cell).floor() as i64)
+    }
+
+    fn endpoint(&self, index: usize, end: End) -> &'a Vec3 {
+        let points = &self.paths[index].points;
+
+        match end {
+            End::Start => &points[0],
+            End::Finish => &points[points.len() - 1],
+        }
+    }
+
+    fn insert(&mut self, index: usize, end: End) {
+        let key = self.key(self.endpoint(index, end));
+
+        self.min = (self.min.0.min(key.0), self.min.1.min(key.1));
+        self.max = (self.max.0.max(key.0), self.max.1.max(key.1));
+        self.cells.entry(key).or_default().push((index, end));
+        self.count += 1;
+    }
+
+    fn remove(&mut self, index: usize) {
+        let ends: &[End] = if self.paths[index].is_dot() {
+            &[End::Start]
+        } else {
+            &[End::Start, End::Finish]
+        };
+
+        for &end in ends {
+            let key = self.key(self.endpoint(index, end));
+
+            if let Some(candidates) = self.cells.get_mut(&key) {
+                let before = candidates.len();
+                candidates.retain(|candidate| *candidate != (index, end));
+                self.count -= before - candidates.len();
+            }
+        }
+    }
+
+    // First unused path with the given end within `distance` of the point.
+    // The cells have to be at least as large as the distance.
+    fn find(&self, point: &Vec3, end: End, used: &[bool], distance: f64) -> Option<usize> {
+        let (x, y) = self.key(point);
+
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let found = self.cells.get(&(x + dx, y + dy)).and_then(|candidates| {
+                    candidates.iter().find(|(index, candidate_end)| {
+                        !used[*index]
+                            && !self.paths[*index].is_dot()
+                            && *candidate_end == end
+                            && length(point, self.endpoint(*index, end)) <= distance
+                    })
+                });
+
+                if let Some((index, _)) = found {
+                    return Some(*index);
+                }
+            }
+        }
+
+        None
+    }
+
+    // Closest endpoint left, searched in growing rings of cells around the
+    // point. Ties go to the lowest index, then to the start. Finishes only
+    // count when paths may be reversed, and then mean the path is reversed.
+    fn nearest(&self, point: &Vec3, reverse: bool) -> Option<(usize, bool)> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let (x, y) = self.key(point);
+        let last_ring = [x - self.min.0, self.max.0 - x, y - self.min.1, self.max.1 - y]
+            .iter()
+            .fold(0, |ring, distance| ring.max(*distance));
+
+        let mut best: Option<(f64, usize, bool)> = None;
+
+        for ring in 0..=last_ring {
+            for (dx, dy) in ring_cells(ring) {
+                let candidates = match self.cells.get(&(x + dx, y + dy)) {
+                    Some(candidates) => candidates,
+                    None => continue,
+                };
+
+                for &(index, end) in candidates {
+                    let reversed = end == End::Finish;
+
+                    if reversed && !reverse {
+                        continue;
+                    }
+
+                    let candidate = (length(point, self.endpoint(index, end)), index, reversed);
+
+                    if best.is_none_or(|best| candidate < best) {
+                        best = Some(candidate);
+                    }
+                }
+            }
+
+            // Everything in the rings further out is at least this far away
+            if best.is_some_and(|(distance, _, _)| distance <= ring as f64 * self.cell) {
+                break;
+            }
+        }
+
+        best.map(|(_, index, reversed)| (index, reversed))
+    }
+}
+
+// Offsets of the cells at a Chebyshev distance of `ring`
+fn ring_cells(ring: i64) -> impl Iterator<Item = (i64, i64)> {
+    let rows = (-ring..=ring).flat_map(move |dx| {
+        let bottom = if ring > 0 { Some((dx, ring)) } else { None };
+        std::iter::once((dx, -ring)).chain(bottom)
+    });
+    let columns = (1 - ring..ring).flat_map(move |dy| std::iter::once((-ring, dy)).chain(Some((ring, dy))));
+
+    rows.chain(columns)
+}
+
+// Chains paths whose ends meet into longer ones, growing every chain both ways
+fn merge(paths: Vec<PenPath>, options: &OptimizeOptions) -> Vec<PenPath> {
+    let endpoints = Endpoints::new(&paths, options.merge_distance);
+    let find = |point: &Vec3, end: End, used: &[bool]| {
+        endpoints.find(point, end, used, options.merge_distance)
+    };
+    let mut used = vec![false; paths.len()];
+    let mut merged = Vec::new();
+
+    for index in 0..paths.len() {
+        if used[index] {
+            continue;
+        }
+
+        used[index] = true;
+
+        if paths[index].is_dot() {
+            merged.push(paths[index].clone());
+            continue;
+        }
+
+        let mut points = paths[index].points.clone();
+
+        loop {
+            let end = points[points.len() - 1];
+
+            if let Some(next) = find(&end, End::Start, &used) {
+                used[next] = true;
+                points.extend_from_slice(&paths[next].points[1..]);
+            } else if let Some(next) = find(&end, End::Finish, &used).filter(|_| options.reverse) {
+                used[next] = true;
+                points.extend(paths[next].points.iter().rev().skip(1));
+            } else {
+                break;
+            }
+        }
+
+        // Pieces that lead into the start, collected backwards
+        let mut before: Vec<Vec3> = Vec::new();
+
+        loop {
+            let start = before.last().copied().unwrap_or(points[0]);
+
+            if let Some(previous) = find(&start, End::Finish, &used) {
+                used[previous] = true;
+                before.extend(paths[previous].points.iter().rev().skip(1));
+            } else if let Some(previous) = find(&start, End::Start, &used).filter(|_| options.reverse) {
+                used[previous] = true;
+                before.extend_from_slice(&paths[previous].points[1..]);
+            } else {
+                break;
+            }
+        }
+
+        before.reverse();
+        before.extend(points);
+
+        merged.push(PenPath { points: before, ..paths[index].clone() });
+    }
+
+    merged
+}
+
+// Greedy order: always continue with the closest path end that is left
+fn nearest_neighbor(paths: &[PenPath], start: &Vec3, reverse: bool) -> Vec<(usize, bool)> {
+    let mut endpoints = Endpoints::new(paths, cell_size(paths));
+    let mut order = Vec::with_capacity(paths.len());
+    let mut position = *start;
+
+    while let Some((index, reversed)) = endpoints.nearest(&position, reverse) {
+        let points = &paths[index].points;
+
+        endpoints.remove(index);
+        order.push((index, reversed));
+        position = if reversed { points[0] } else { points[points.len() - 1] };
+    }
+
+    order
+}
+
+// Grid spacing with about one endpoint per cell, for paths spread over an
+// area or along a line alike
+fn cell_size(paths: &[PenPath]) -> f64 {
+    let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+    let (mut max_x, mut max_y) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+
+    for path in paths {
+        for point in [&path.points[0], &path.points[path.points.len() - 1]].iter() {
+            min_x = min_x.min(point.x);
+            min_y = min_y.min(point.y);
+            max_x = max_x.max(point.x);
+            max_y = max_y.max(point.y);
+        }
+    }
+
+    let count = (paths.len() * 2).max(1) as f64;
+    let (width, height) = (max_x - min_x, max_y - min_y);
+
+    f64::max(f64::sqrt(width * height / count), width.max(height) / count)
+}
+
+// Reversing a run of the order also flips the direction of every path in
+// it, so this is only possible when paths may be reversed. Only runs up to
+// TWO_OPT_WINDOW long are tried, which keeps a pass linear.
+fn two_opt(paths: &[PenPath], order: &mut [(usize, bool)], start: &Vec3) {
+    let first = |(index, reversed): (usize, bool)| {
+        let points = &paths[index].points;
+        if reversed { points[points.len() - 1] } else { points[0] }
+    };
+
+    let last = |(index, reversed): (usize, bool)| {
+        let points = &paths[index].points;
+        if reversed { points[0] } else { points[points.len() - 1] }
+    };
+
+    for _ in 0..MAX_TWO_OPT_PASSES {
+        let mut improved = false;
+
+        for i in 0..order.len() {
+            let before = if i == 0 { *start } else { last(order[i - 1]) };
+
+            for j in i + 1..order.len().min(i + TWO_OPT_WINDOW) {
+                let mut old = length(&before, &first(order[i]));
+                let mut new = length(&before, &last(order[j]));
+
+                if j + 1 < order.len() {
+                    let after = first(order[j + 1]);
+                    old += length(&last(order[j]), &after);
+                    new += length(&first(order[i]), &after);
+                }
+
+                if new < old - 1e-9 {
+                    order[i..=j].reverse();
+
+                    for item in &mut order[i..=j] {
+                        item.1 = !item.1;
+                    }
+
+                    improved = true;
+                }
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+}
+
+fn length(a: &Vec3, b: &Vec3) -> f64 {
+    f64::hypot(b.x - a.x, b.y - a.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::colors::Color;
+
+    fn path(points: &[(f64, f64)]) -> PenPath {
+        PenPath {
+            color: Color::rgb(0.0, 0.0, 0.0),
+            layer: None,
+            points: points.iter().map(|(x, y)| vec3!(*x, *y, 1.0)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_merge() {
+        let paths = vec![
+            path(&[(1.0, 0.0), (2.0, 0.0)]),
+            path(&[(3.0, 0.0), (2.0, 0.0)]),
+            path(&[(0.0, 0.0), (1.0, 0.0)]),
+            path(&[(5.0, 5.0)]),
+        ];
+
+        let merged = merge(paths.clone(), &OptimizeOptions::default());
+
+        assert_eq!(merged, vec![
+            path(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0)]),
+            path(&[(5.0, 5.0)]),
+        ]);
+
+        // The second path can only join its own end, so it stays apart
+        let merged = merge(paths, &OptimizeOptions { reverse: false, ..OptimizeOptions::default() });
+
+        assert_eq!(merged, vec![
+            path(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)]),
+            path(&[(3.0, 0.0), (2.0, 0.0)]),
+            path(&[(5.0, 5.0)]),
+        ]);
+    }
+
+    #[test]
+    fn test_optimize_reduces_travel() {
+        // Short strokes along a line, drawn in a scattered order
+        let paths: Vec<PenPath> = (0..20)
+            .map(|i| ((i * 7) % 20) as f64 * 10.0)
+            .map(|x| path(&[(x, 0.0), (x + 5.0, 0.0)]))
+            .collect();
+
+        let (optimized, report) = optimize(paths.clone(), &OptimizeOptions::default());
+
+        assert_eq!(optimized.len(), paths.len());
+        assert_eq!(report.before, pen_up_distance(&paths));
+        assert_eq!(report.after, pen_up_distance(&optimized));
+        // Along the line and back home
+        assert!((report.after - 2.0 * 195.0 + 100.0).abs() < 1e-9, "{:?}", report);
+    }
+
+    #[test]
+    fn test_pens_stay_together() {
+        let red = PenPath { color: Color::rgb(1.0, 0.0, 0.0), ..path(&[(0.0, 1.0), (0.0, 2.0)]) };
+        let paths = vec![path(&[(0.0, 0.0)]), red.clone(), path(&[(0.0, 3.0)])];
+
+        let (optimized, _) = optimize(paths, &OptimizeOptions::default());
+
+        // Red starts from the end closest to where black left off
+        assert_eq!(optimized, vec![
+            path(&[(0.0, 0.0)]),
+            path(&[(0.0, 3.0)]),
+            PenPath { color: red.color, ..path(&[(0.0, 2.0), (0.0, 1.0)]) },
+        ]);
+    }
+
+    #[test]
+    fn test_optimize_many_paths() {
+        // Short strokes scattered over an A3 sheet, by a linear congruential generator
+        let mut seed: u64 = 1;
+        let mut random = move || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+
+        let paths: Vec<PenPath> = (0..20_000)
+            .map(|_| {
+                let (x, y) = (random() * 420.0, random() * 297.0);
+                path(&[(x, y), (x + random() * 4.0 - 2.0, y + random() * 4.0 - 2.0)])
+            })
+            .collect();
+
+        let started = std::time::Instant::now();
+        let (optimized, report) = optimize(paths, &OptimizeOptions::default());
+
+        assert!(started.elapsed().as_secs_f64() < 10.0, "{:?}", started.elapsed());
+        // A few strokes happen to meet and are merged
+        assert!(optimized.len() > 19_900);
+        assert!(report.after < report.before / 10.0, "{:?}", report);
+
+        // Below the 2-opt limit as well
+        let (optimized, _) = optimize(optimized[..MAX_TWO_OPT_PATHS].to_vec(), &OptimizeOptions::default());
+
+        assert!(started.elapsed().as_secs_f64() < 20.0, "{:?}", started.elapsed());
+        assert_eq!(optimized.len(), MAX_TWO_OPT_PATHS);
+    }
+
+    #[test]
+    fn test_nearest_endpoint() {
+        let paths = vec![
+            path(&[(0.0, 0.0), (10.0, 0.0)]),
+            path(&[(3.2, 0.0)]),
+            path(&[(50.0, 50.0), (2.0, 0.0)]),
+        ];
+
+        let mut endpoints = Endpoints::new(&paths, 1.0);
+        let point = vec3!(2.5, 0.0, 1.0);
+
+        assert_eq!(endpoints.nearest(&point, true), Some((2, true)));
+        assert_eq!(endpoints.nearest(&point, false), Some((1, false)));
+
+        endpoints.remove(1);
+        endpoints.remove(2);
+        assert_eq!(endpoints.nearest(&point, true), Some((0, false)));
+        assert_eq!(endpoints.nearest(&vec3!(100.0, 0.0, 1.0), true), Some((0, true)));
+
+        endpoints.remove(0);
+        assert_eq!(endpoints.nearest(&point, true), None);
+    }
+
+    #[test]
+    fn test_two_opt() {
+        let paths = vec![
+            path(&[(0.0, 0.0), (0.0, 1.0)]),
+            path(&[(10.0, 1.0), (10.0, 0.0)]),
+            path(&[(5.0, 0.0), (5.0, 1.0)]),
+        ];
+
+        let mut order = vec![(0, false), (1, false), (2, false)];
+        two_opt(&paths, &mut order, &vec3!(0.0, 0.0, 1.0));
+
+        assert_eq!(order, vec![(0, false), (2, true), (1, true)]);
+    }
+}
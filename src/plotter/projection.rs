@@ -0,0 +1,389 @@
+use crate::math::bounds::Bounds;
+use crate::math::clip::clip_line;
+use crate::math::ellipse::EllipticalArc;
+use crate::math::mat3::Mat3;
+use crate::math::vec3::Vec3;
+use crate::vec3;
+
+use super::path::Path;
+use super::{Grid, Shape, Text, TextSpace};
+
+// Points are drawn as discs of this radius, in pixels
+pub const POINT_RADIUS: f64 = 3.0;
+
+// Closest that adaptive grid lines get before they fade out, in pixels
+const MIN_GRID_SPACING: f64 = 8.0;
+
+// Fixed grid lines any closer than this are not drawn at all
+const MIN_VISIBLE_GRID_SPACING: f64 = 2.0;
+
+// Grid lines by how they are drawn
+pub struct GridPaths {
+    pub minor: Path,
+    // How far the minor lines have faded in, from 0 to 1
+    pub minor_alpha: f64,
+    pub major: Path,
+    pub axes: Path,
+}
+
+// World to canvas mapping, and tracing of shapes into canvas space paths
+pub struct Projection {
+    transform: Mat3,
+    inverse_transform: Mat3,
+    canvas_size: (f64, f64),
+}
+
+impl Projection {
+    pub fn new() -> Self {
+        Projection {
+            transform: Mat3::identity(),
+            inverse_transform: Mat3::identity(),
+            canvas_size: (0.0, 0.0),
+        }
+    }
+
+    pub fn transform(&self) -> &Mat3 {
+        &self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Mat3) {
+        let mut inverse_transform = transform.clone();
+        inverse_transform.inverse();
+
+        self.transform = transform;
+        self.inverse_transform = inverse_transform;
+    }
+
+    // Projection of a space that `transform` places within this world
+    pub fn local(&self, transform: &Mat3) -> Projection {
+        let mut local = Projection::new();
+        local.set_canvas_size(self.canvas_size);
+        local.set_transform(&self.transform * transform);
+        local
+    }
+
+    pub fn canvas_size(&self) -> (f64, f64) {
+        self.canvas_size
+    }
+
+    pub fn set_canvas_size(&mut self, canvas_size: (f64, f64)) {
+        self.canvas_size = canvas_size;
+    }
+
+    pub fn project_to_canvas(&self, point: &Vec3) -> Vec3 {
+        &self.transform * point
+    }
+
+    pub fn unproject_from_canvas(&self, point: &Vec3) -> Vec3 {
+        &self.inverse_transform * point
+    }
+
+    // Visible area of the canvas in world space. It is a parallelogram
+    // rather than a rectangle once the transform contains a rotation.
+    pub fn viewport(&self) -> [Vec3; 4] {
+        let (width, height) = self.canvas_size;
+
+        [
+            self.unproject_from_canvas(&vec3!(0.0, 0.0, 1.0)),
+            self.unproject_from_canvas(&vec3!(width, 0.0, 1.0)),
+            self.unproject_from_canvas(&vec3!(width, height, 1.0)),
+            self.unproject_from_canvas(&vec3!(0.0, height, 1.0)),
+        ]
+    }
+
+    pub fn trace(&self, shape: &Shape, path: &mut Path) {
+        match shape {
+            Shape::Point(origin) => self.trace_point(path, origin),
+            Shape::Segment(from, to) => self.trace_segment(path, from, to),
+            Shape::Ray(origin, direction) => self.trace_ray(path, origin, direction),
+            Shape::Line(from, to) => self.trace_line(path, from, to),
+            Shape::Circle { center, radius } => {
+                self.trace_arc(path, &EllipticalArc::full(*center, *radius, *radius, 0.0))
+            }
+            Shape::Arc { center, radius, start, end } => {
+                self.trace_arc(path, &EllipticalArc {
+                    center: *center,
+                    rx: *radius,
+                    ry: *radius,
+                    rotation: 0.0,
+                    start: *start,
+                    end: *end,
+                    anticlockwise: false,
+                })
+            }
+            Shape::Ellipse { center, rx, ry, rotation } => {
+                self.trace_arc(path, &EllipticalArc::full(*center, *rx, *ry, *rotation))
+            }
+            Shape::Polyline(points) => self.trace_polyline(path, points, false),
+            Shape::Polygon(points) => self.trace_polyline(path, points, true),
+            Shape::QuadraticBezier(from, control, to) => {
+                self.trace_quadratic_bezier(path, from, control, to)
+            }
+            Shape::CubicBezier(from, control1, control2, to) => {
+                self.trace_cubic_bezier(path, from, control1, control2, to)
+            }
+            Shape::Text(_) => (),
+            Shape::Grid(grid) => {
+                let grid = self.trace_grid(grid);
+
+                for part in [grid.minor, grid.major, grid.axes] {
+                    path.commands.extend(part.commands);
+                }
+            }
+        }
+    }
+
+    fn trace_point(&self, path: &mut Path, origin: &Vec3) {
+        let target = self.project_to_canvas(origin);
+
+        path.move_to(target.x + POINT_RADIUS, target.y);
+        path.ellipse(EllipticalArc::full(target, POINT_RADIUS, POINT_RADIUS, 0.0));
+    }
+
+    fn trace_segment(&self, path: &mut Path, from: &Vec3, to: &Vec3) {
+        let actual_from = self.project_to_canvas(from);
+        let actual_to = self.project_to_canvas(to);
+
+        path.move_to(actual_from.x, actual_from.y);
+        path.line_to(actual_to.x, actual_to.y);
+    }
+
+    fn trace_ray(&self, path: &mut Path, origin: &Vec3, direction: &Vec3) {
+        self.trace_clipped(path, origin, direction, 0.0, f64::INFINITY);
+    }
+
+    fn trace_line(&self, path: &mut Path, from: &Vec3, to: &Vec3) {
+        let direction = vec3!(to.x - from.x, to.y - from.y, 0.0);
+        self.trace_clipped(path, from, &direction, f64::NEG_INFINITY, f64::INFINITY);
+    }
+
+    fn trace_clipped(&self, path: &mut Path, origin: &Vec3, direction: &Vec3, t_min: f64, t_max: f64) {
+        let viewport = self.viewport();
+
+        if let Some((from, to)) = clip_line(origin, direction, t_min, t_max, &viewport) {
+            self.trace_segment(
+                path,
+                &vec3!(origin.x + direction.x * from, origin.y + direction.y * from, 1.0),
+                &vec3!(origin.x + direction.x * to, origin.y + direction.y * to, 1.0),
+            );
+        }
+    }
+
+    fn trace_arc(&self, path: &mut Path, arc: &EllipticalArc) {
+        path.ellipse(arc.transform(&self.transform));
+    }
+
+    fn trace_polyline(&self, path: &mut Path, points: &[Vec3], closed: bool) {
+        for (i, point) in points.iter().enumerate() {
+            let target = self.project_to_canvas(point);
+
+            if i == 0 {
+                path.move_to(target.x, target.y);
+            } else {
+                path.line_to(target.x, target.y);
+            }
+        }
+
+        if closed {
+            path.close_path();
+        }
+    }
+
+    fn trace_quadratic_bezier(&self, path: &mut Path, from: &Vec3, control: &Vec3, to: &Vec3) {
+        let actual_from = self.project_to_canvas(from);
+        let actual_control = self.project_to_canvas(control);
+        let actual_to = self.project_to_canvas(to);
+
+        path.move_to(actual_from.x, actual_from.y);
+        path.quadratic_curve_to(actual_control.x, actual_control.y, actual_to.x, actual_to.y);
+    }
+
+    fn trace_cubic_bezier(
+        &self,
+        path: &mut Path,
+        from: &Vec3,
+        control1: &Vec3,
+        control2: &Vec3,
+        to: &Vec3,
+    ) {
+        let actual_from = self.project_to_canvas(from);
+        let actual_control1 = self.project_to_canvas(control1);
+        let actual_control2 = self.project_to_canvas(control2);
+        let actual_to = self.project_to_canvas(to);
+
+        path.move_to(actual_from.x, actual_from.y);
+        path.bezier_curve_to(
+            actual_control1.x,
+            actual_control1.y,
+            actual_control2.x,
+            actual_control2.y,
+            actual_to.x,
+            actual_to.y,
+        );
+    }
+
+    // Maps the text space, with the anchor at the origin, onto the canvas
+    pub fn text_placement(&self, text: &Text) -> Mat3 {
+        let target = self.project_to_canvas(&text.anchor);
+
+        match text.space {
+            TextSpace::Screen => Mat3::identity().translate(&target),
+            TextSpace::World => Mat3 {
+                m13: target.x,
+                m23: target.y,
+                ..self.transform.clone()
+            },
+        }
+    }
+
+    // Lines of the grid within the exact visible area of the world
+    pub fn trace_grid(&self, grid: &Grid) -> GridPaths {
+        let mut paths = GridPaths {
+            minor: Path::new(),
+            minor_alpha: 0.0,
+            major: Path::new(),
+            axes: Path::new(),
+        };
+
+        let scale = self.transform.linear_scale();
+        let visible = match Bounds::from_points(&self.viewport()) {
+            Some(visible) if grid.step > 0.0 && scale > 0.0 => visible,
+            _ => return paths,
+        };
+
+        let subdivisions = grid.subdivisions.max(1) as f64;
+
+        let (major_step, minor_step) = if grid.adaptive {
+            let factor = subdivisions.max(2.0);
+            let base = grid.step / factor;
+
+            // Smallest power of the factor that keeps minor lines far enough apart
+            let level = (f64::ln(MIN_GRID_SPACING / (base * scale)) / f64::ln(factor)).ceil();
+            let minor_step = base * factor.powf(level);
+
+            // Minor lines fade out as they get closer, and are as strong as
+            // the major lines just before they take their place
+            let fade = f64::ln(minor_step * scale / MIN_GRID_SPACING) / f64::ln(factor);
+            paths.minor_alpha = fade.clamp(0.0, 1.0);
+
+            (minor_step * factor, Some((minor_step, factor)))
+        } else {
+            paths.minor_alpha = 1.0;
+            (grid.step, Some((grid.step / subdivisions, subdivisions)).filter(|_| subdivisions > 1.0))
+        };
+
+        if let Some((step, factor)) = minor_step {
+            if step * scale >= MIN_VISIBLE_GRID_SPACING {
+                let factor = factor as i64;
+                self.trace_grid_lines(&mut paths.minor, &visible, step, |i| i % factor != 0);
+            }
+        }
+
+        let axes = grid.axis_color.is_some();
+
+        if major_step * scale >= MIN_VISIBLE_GRID_SPACING {
+            self.trace_grid_lines(&mut paths.major, &visible, major_step, |i| !axes || i != 0);
+        }
+
+        if axes {
+            let origin = vec3!(0.0, 0.0, 1.0);
+            self.trace_line(&mut paths.axes, &origin, &vec3!(1.0, 0.0, 1.0));
+            self.trace_line(&mut paths.axes, &origin, &vec3!(0.0, 1.0, 1.0));
+        }
+
+        paths
+    }
+
+    // Vertical and horizontal lines at the multiples of `step` kept by `filter`
+    fn trace_grid_lines(&self, path: &mut Path, visible: &Bounds, step: f64, filter: impl Fn(i64) -> bool) {
+        let indices = |min: f64, max: f64| {
+            ((min / step).ceil() as i64..=(max / step).floor() as i64).filter(|i| filter(*i))
+        };
+
+        for i in indices(visible.min_x, visible.max_x) {
+            let x = step * (i as f64);
+            self.trace_line(path, &vec3!(x, 0.0, 1.0), &vec3!(x, 1.0, 1.0));
+        }
+
+        for i in indices(visible.min_y, visible.max_y) {
+            let y = step * (i as f64);
+            self.trace_line(path, &vec3!(0.0, y, 1.0), &vec3!(1.0, y, 1.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::colors::Color;
+    use crate::plotter::path::PathCommand;
+
+    fn projection(transform: Mat3) -> Projection {
+        let mut projection = Projection::new();
+        projection.set_canvas_size((100.0, 100.0));
+        projection.set_transform(transform);
+        projection
+    }
+
+    fn lines(path: &Path) -> usize {
+        path.commands
+            .iter()
+            .filter(|command| matches!(command, PathCommand::MoveTo(_, _)))
+            .count()
+    }
+
+    #[test]
+    fn test_grid_lines() {
+        let grid = Grid { subdivisions: 2, ..Grid::new(20.0) };
+
+        // The world from 5 to 105 has major lines at 20 to 100 and minor ones at 10 to 90
+        let paths = projection(Mat3::identity().translate(&vec3!(-5.0, -5.0, 1.0))).trace_grid(&grid);
+
+        assert_eq!(lines(&paths.major), 10);
+        assert_eq!(lines(&paths.minor), 10);
+        assert_eq!(paths.minor_alpha, 1.0);
+        assert!(paths.axes.is_empty());
+
+        // Rotated, the lines cover the bounds of the whole visible area
+        let rotated = Mat3::identity()
+            .translate(&vec3!(50.0, 50.0, 1.0))
+            .rotate(std::f64::consts::PI / 4.0);
+        let paths = projection(rotated).trace_grid(&Grid::new(10.0));
+
+        assert_eq!(lines(&paths.major), 30);
+    }
+
+    #[test]
+    fn test_grid_axes() {
+        let grid = Grid { axis_color: Some(Color::rgb(1.0, 0.0, 0.0)), ..Grid::new(10.0) };
+        let paths = projection(Mat3::identity().translate(&vec3!(50.0, 50.0, 1.0))).trace_grid(&grid);
+
+        assert_eq!(lines(&paths.axes), 2);
+        assert_eq!(lines(&paths.major), 20);
+    }
+
+    #[test]
+    fn test_adaptive_grid() {
+        let grid = Grid { subdivisions: 10, adaptive: true, ..Grid::new(100.0) };
+
+        // Minor lines 10 pixels apart have barely faded in
+        let offset = Mat3::identity().translate(&vec3!(5.0, 5.0, 1.0));
+        let paths = projection(offset.clone()).trace_grid(&grid);
+
+        assert_eq!(lines(&paths.minor), 18);
+        assert_eq!(lines(&paths.major), 2);
+        assert!(paths.minor_alpha > 0.0 && paths.minor_alpha < 0.2);
+
+        // Zooming out by ten moves up a level, with the same lines on screen
+        let zoomed = projection(offset.scale(&vec3!(0.1, 0.1, 0.0))).trace_grid(&grid);
+
+        assert_eq!(lines(&zoomed.minor), 18);
+        assert_eq!(lines(&zoomed.major), 2);
+        assert!((zoomed.minor_alpha - paths.minor_alpha).abs() < 1e-9);
+
+        // Zooming in makes them stronger until they take over from the major lines
+        let closer = projection(Mat3::identity().scale(&vec3!(5.0, 5.0, 0.0))).trace_grid(&grid);
+
+        assert!(closer.minor_alpha > 0.75);
+    }
+}
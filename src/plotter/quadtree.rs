@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use crate::math::bounds::Bounds;
+
+// Items a node keeps before it splits into quadrants
+const NODE_CAPACITY: usize = 8;
+const MAX_DEPTH: usize = 24;
+
+// Quadtree over item bounds. Items stay in the smallest node that fully
+// contains them, and the root grows whenever something is added outside.
+pub struct QuadTree {
+    root: Option<Node>,
+    items: HashMap<u32, Bounds>,
+}
+
+struct Node {
+    // Always square
+    bounds: Bounds,
+    items: Vec<(u32, Bounds)>,
+    children: Option<Box<[Node; 4]>>,
+}
+
+impl QuadTree {
+    pub fn new() -> Self {
+        QuadTree {
+            root: None,
+            items: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    // Bounds have to be finite. An item that is already in the tree is moved.
+    pub fn insert(&mut self, item: u32, bounds: Bounds) {
+        self.remove(item);
+
+        let root = self.root.get_or_insert_with(|| {
+            let size = f64::max(bounds.width(), bounds.height()).max(1.0);
+
+            Node::new(Bounds {
+                min_x: bounds.min_x,
+                min_y: bounds.min_y,
+                max_x: bounds.min_x + size,
+                max_y: bounds.min_y + size,
+            })
+        });
+
+        while !root.bounds.contains(&bounds) {
+            root.grow_towards(&bounds);
+        }
+
+        root.insert(item, bounds, 0);
+        self.items.insert(item, bounds);
+    }
+
+    pub fn remove(&mut self, item: u32) -> bool {
+        match (self.items.remove(&item), self.root.as_mut()) {
+            (Some(bounds), Some(root)) => root.remove(item, &bounds),
+            _ => false,
+        }
+    }
+
+    // Items whose bounds intersect the area, in no particular order
+    pub fn query(&self, area: &Bounds, found: &mut Vec<u32>) {
+        if let Some(root) = &self.root {
+            root.query(area, found);
+        }
+    }
+}
+
+impl Node {
+    fn new(bounds: Bounds) -> Self {
+        Node {
+            bounds,
+            items: Vec::new(),
+            children: None,
+        }
+    }
+
+    fn quadrants(&self) -> [Bounds; 4] {
+        let b = &self.bounds;
+        let middle_x = (b.min_x + b.max_x) / 2.0;
+        let middle_y = (b.min_y + b.max_y) / 2.0;
+
+        [
+            Bounds { min_x: b.min_x, min_y: b.min_y, max_x: middle_x, max_y: middle_y },
+            Bounds { min_x: middle_x, min_y: b.min_y, max_x: b.max_x, max_y: middle_y },
+            Bounds { min_x: b.min_x, min_y: middle_y, max_x: middle_x, max_y: b.max_y },
+            Bounds { min_x: middle_x, min_y: middle_y, max_x: b.max_x, max_y: b.max_y },
+        ]
+    }
+
+    fn quadrant_of(&self, bounds: &Bounds) -> Option<usize> {
+        self.children
+            .as_ref()?
+            .iter()
+            .position(|child| child.bounds.contains(bounds))
+    }
+
+    // Doubles the node, which becomes one of the quadrants of the new one
+    fn grow_towards(&mut self, bounds: &Bounds) {
+        let size = self.bounds.width();
+        let left = bounds.min_x < self.bounds.min_x;
+        let up = bounds.min_y < self.bounds.min_y;
+
+        let min_x = if left { self.bounds.min_x - size } else { self.bounds.min_x };
+        let min_y = if up { self.bounds.min_y - size } else { self.bounds.min_y };
+
+        let mut grown = Node::new(Bounds {
+            min_x,
+            min_y,
+            max_x: min_x + size * 2.0,
+            max_y: min_y + size * 2.0,
+        });
+
+        let quadrants = grown.quadrants();
+        let old = std::mem::replace(self, Node::new(quadrants[0]));
+        let position = (if left { 1 } else { 0 }) + (if up { 2 } else { 0 });
+
+        let mut children = quadrants.map(Node::new);
+        children[position] = old;
+        grown.children = Some(Box::new(children));
+
+        *self = grown;
+    }
+
+    fn insert(&mut self, item: u32, bounds: Bounds, depth: usize) {
+        if let Some(quadrant) = self.quadrant_of(&bounds) {
+            self.children.as_mut().unwrap()[quadrant].insert(item, bounds, depth + 1);
+            return;
+        }
+
+        self.items.push((item, bounds));
+
+        if self.children.is_none() && self.items.len() > NODE_CAPACITY && depth < MAX_DEPTH {
+            self.children = Some(Box::new(self.quadrants().map(Node::new)));
+
+            for (item, bounds) in std::mem::take(&mut self.items) {
+                self.insert(item, bounds, depth);
+            }
+        }
+    }
+
+    // Items on the edge between quadrants fit in more than one of them, and
+    // the one picked on insert may not come first once the root has grown
+    // around the node. So every child that contains the item is searched.
+    fn remove(&mut self, item: u32, bounds: &Bounds) -> bool {
+        if let Some(position) = self.items.iter().position(|(other, _)| *other == item) {
+            self.items.swap_remove(position);
+            return true;
+        }
+
+        match &mut self.children {
+            Some(children) => children
+                .iter_mut()
+                .filter(|child| child.bounds.contains(bounds))
+                .any(|child| child.remove(item, bounds)),
+            None => false,
+        }
+    }
+
+    fn query(&self, area: &Bounds, found: &mut Vec<u32>) {
+        if !self.bounds.intersects(area) {
+            return;
+        }
+
+        for (item, bounds) in &self.items {
+            if bounds.intersects(area) {
+                found.push(*item);
+            }
+        }
+
+        if let Some(children) = &self.children {
+            for child in children.iter() {
+                child.query(area, found);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::vec3::Vec3;
+    use crate::vec3;
+
+    fn query(tree: &QuadTree, area: &Bounds) -> Vec<u32> {
+        let mut found = Vec::new();
+        tree.query(area, &mut found);
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn test_query() {
+        let mut tree = QuadTree::new();
+
+        for i in 0..100 {
+            let x = (i % 10) as f64 * 10.0;
+            let y = (i / 10) as f64 * 10.0;
+            tree.insert(i, Bounds::around(&vec3!(x, y, 1.0), 1.0));
+        }
+
+        // Something big and far away makes the root grow in the other direction
+        tree.insert(100, Bounds { min_x: -500.0, min_y: -20.0, max_x: 5.0, max_y: -10.0 });
+
+        assert_eq!(tree.len(), 101);
+        assert_eq!(query(&tree, &Bounds::around(&vec3!(15.0, 15.0, 1.0), 5.0)), vec![11, 12, 21, 22]);
+        assert_eq!(query(&tree, &Bounds::around(&vec3!(-300.0, -15.0, 1.0), 1.0)), vec![100]);
+        assert!(query(&tree, &Bounds::around(&vec3!(1000.0, 0.0, 1.0), 1.0)).is_empty());
+    }
+
+    #[test]
+    fn test_move_and_remove() {
+        let mut tree = QuadTree::new();
+
+        for i in 0..50 {
+            tree.insert(i, Bounds::around(&vec3!(i as f64, 0.0, 1.0), 0.1));
+        }
+
+        tree.insert(7, Bounds::around(&vec3!(-100.0, -100.0, 1.0), 0.1));
+
+        assert!(tree.remove(8));
+        assert!(!tree.remove(8));
+        assert_eq!(tree.len(), 49);
+        assert_eq!(query(&tree, &Bounds::around(&vec3!(8.0, 0.0, 1.0), 1.0)), vec![9]);
+        assert_eq!(query(&tree, &Bounds::around(&vec3!(-100.0, -100.0, 1.0), 1.0)), vec![7]);
+    }
+
+    #[test]
+    fn test_remove_on_edge_after_growth() {
+        let mut tree = QuadTree::new();
+        let point = Bounds::around(&vec3!(100.0, 100.0, 1.0), 0.0);
+
+        // The root starts at the point, which ends up on the edge between
+        // quadrants as the root grows towards the origin
+        tree.insert(0, point);
+        tree.insert(1, Bounds { min_x: 0.0, min_y: 0.0, max_x: 100.0, max_y: 100.0 });
+        tree.insert(0, Bounds::around(&vec3!(50.0, 50.0, 1.0), 0.0));
+
+        assert_eq!(query(&tree, &Bounds::around(&vec3!(50.0, 50.0, 1.0), 100.0)), vec![0, 1]);
+        assert_eq!(query(&tree, &point), vec![1]);
+
+        assert!(tree.remove(0));
+        assert!(tree.remove(1));
+        assert!(query(&tree, &Bounds::around(&vec3!(50.0, 50.0, 1.0), 100.0)).is_empty());
+    }
+}
@@ -0,0 +1,747 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::math::bounds::Bounds;
+use crate::math::mat3::Mat3;
+use crate::math::vec3::Vec3;
+use crate::vec3;
+
+use super::group::{Group, GroupId};
+use super::layer::{Layer, LayerId};
+use super::quadtree::QuadTree;
+use super::Primitive;
+
+// Stable reference to a primitive. Handles of removed primitives never
+// resolve again, even after their slot is reused for a new primitive.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct PrimitiveHandle {
+    index: u32,
+    generation: u32,
+}
+
+// Primitives are drawn layer by layer, then by z_index, and in insertion
+// order within equal z_index. Primitives without a layer are drawn as if
+// they were on a layer at z_index 0 that comes before all others.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+struct OrderKey {
+    layer_z_index: i32,
+    layer: Option<LayerId>,
+    z_index: i32,
+    sequence: u64,
+}
+
+struct Entry {
+    primitive: Primitive,
+    layer: Option<LayerId>,
+    group: Option<GroupId>,
+    order: OrderKey,
+    visible: bool,
+    // Handed out through get_mut since the last draw order update
+    dirty: bool,
+}
+
+struct Slot {
+    generation: u32,
+    entry: Option<Entry>,
+}
+
+pub struct Scene {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+    draw_order: BTreeMap<OrderKey, u32>,
+    dirty: Vec<u32>,
+    next_sequence: u64,
+    // Primitives by their bounds, and the ones that have none
+    index: QuadTree,
+    unbounded: BTreeSet<u32>,
+    // Largest screen_reach of any primitive ever added
+    screen_reach: f64,
+    layers: Vec<Layer>,
+    // The z_index of each layer that its primitives are keyed by in draw_order
+    layer_z_indices: Vec<i32>,
+    // Handed out through layer_mut since the last update
+    layers_dirty: bool,
+    // In the order they were added, so parents always come before children
+    groups: Vec<Group>,
+    // Some world transform is stale
+    groups_dirty: bool,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Scene {
+            slots: Vec::new(),
+            free: Vec::new(),
+            draw_order: BTreeMap::new(),
+            dirty: Vec::new(),
+            next_sequence: 0,
+            index: QuadTree::new(),
+            unbounded: BTreeSet::new(),
+            screen_reach: 0.0,
+            layers: Vec::new(),
+            layer_z_indices: Vec::new(),
+            layers_dirty: false,
+            groups: Vec::new(),
+            groups_dirty: false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.draw_order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.draw_order.is_empty()
+    }
+
+    pub fn add(&mut self, primitive: Primitive) -> PrimitiveHandle {
+        self.add_to(None, primitive)
+    }
+
+    pub fn add_to(&mut self, layer: Option<LayerId>, primitive: Primitive) -> PrimitiveHandle {
+        let layer = layer.filter(|id| self.layer(*id).is_some());
+
+        let order = OrderKey {
+            layer_z_index: layer.map_or(0, |id| self.layer_z_indices[id.0 as usize]),
+            layer,
+            z_index: primitive.z_index,
+            sequence: self.next_sequence,
+        };
+
+        self.next_sequence += 1;
+
+        let entry = Entry {
+            primitive,
+            layer,
+            group: None,
+            order,
+            visible: true,
+            dirty: false,
+        };
+
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.slots[index as usize].entry = Some(entry);
+                index
+            }
+            None => {
+                self.slots.push(Slot { generation: 0, entry: Some(entry) });
+                (self.slots.len() - 1) as u32
+            }
+        };
+
+        self.draw_order.insert(order, index);
+        self.reindex(index);
+
+        PrimitiveHandle {
+            index,
+            generation: self.slots[index as usize].generation,
+        }
+    }
+
+    pub fn remove(&mut self, handle: PrimitiveHandle) -> Option<Primitive> {
+        self.entry(handle)?;
+
+        let slot = &mut self.slots[handle.index as usize];
+        let entry = slot.entry.take()?;
+
+        self.draw_order.remove(&entry.order);
+        self.index.remove(handle.index);
+        self.unbounded.remove(&handle.index);
+
+        if let Some(group) = entry.group {
+            self.groups[group.0 as usize].members.remove(&handle.index);
+        }
+
+        // A slot that ran out of generations is never reused, so that
+        // old handles can't alias whatever would be put there next
+        if slot.generation < u32::MAX {
+            slot.generation += 1;
+            self.free.push(handle.index);
+        }
+
+        Some(entry.primitive)
+    }
+
+    pub fn contains(&self, handle: PrimitiveHandle) -> bool {
+        self.entry(handle).is_some()
+    }
+
+    pub fn get(&self, handle: PrimitiveHandle) -> Option<&Primitive> {
+        self.entry(handle).map(|entry| &entry.primitive)
+    }
+
+    // The primitive may be changed through the returned reference, so its
+    // place in the draw order and the index is checked on the next update
+    pub fn get_mut(&mut self, handle: PrimitiveHandle) -> Option<&mut Primitive> {
+        let entry = self.slots
+            .get_mut(handle.index as usize)
+            .filter(|slot| slot.generation == handle.generation)?
+            .entry
+            .as_mut()?;
+
+        if !entry.dirty {
+            entry.dirty = true;
+            self.dirty.push(handle.index);
+        }
+
+        Some(&mut entry.primitive)
+    }
+
+    pub fn layer_of(&self, handle: PrimitiveHandle) -> Option<LayerId> {
+        self.entry(handle)?.layer
+    }
+
+    pub fn add_layer(&mut self, layer: Layer) -> LayerId {
+        self.layer_z_indices.push(layer.z_index);
+        self.layers.push(layer);
+        LayerId((self.layers.len() - 1) as u32)
+    }
+
+    pub fn layer(&self, id: LayerId) -> Option<&Layer> {
+        self.layers.get(id.0 as usize)
+    }
+
+    // Like get_mut, a changed z_index is picked up on the next update
+    pub fn layer_mut(&mut self, id: LayerId) -> Option<&mut Layer> {
+        self.layers_dirty = true;
+        self.layers.get_mut(id.0 as usize)
+    }
+
+    pub fn find_layer(&self, name: &str) -> Option<LayerId> {
+        self.layers
+            .iter()
+            .position(|layer| layer.name == name)
+            .map(|index| LayerId(index as u32))
+    }
+
+    // Visible layers in the order they are drawn, with None standing for
+    // the primitives that are on no layer
+    pub fn layers_in_order(&self) -> Vec<Option<LayerId>> {
+        let mut order: Vec<(i32, Option<LayerId>)> = self.layers
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| layer.visible)
+            .map(|(index, layer)| (layer.z_index, Some(LayerId(index as u32))))
+            .collect();
+
+        order.push((0, None));
+        order.sort_unstable();
+
+        order.into_iter().map(|(_, id)| id).collect()
+    }
+
+    pub fn add_group(&mut self, parent: Option<GroupId>, transform: Mat3) -> GroupId {
+        let parent = parent.filter(|id| (id.0 as usize) < self.groups.len());
+        let id = GroupId(self.groups.len() as u32);
+
+        if let Some(parent) = parent {
+            self.groups[parent.0 as usize].children.push(id);
+        }
+
+        self.groups.push(Group::new(parent, transform));
+        self.groups_dirty = true;
+
+        id
+    }
+
+    pub fn group_transform(&self, id: GroupId) -> Option<&Mat3> {
+        self.groups.get(id.0 as usize).map(|group| &group.transform)
+    }
+
+    // The world transforms of the group and everything below it are
+    // composed again on the next update
+    pub fn set_group_transform(&mut self, id: GroupId, transform: Mat3) {
+        if let Some(group) = self.groups.get_mut(id.0 as usize) {
+            group.transform = transform;
+            self.invalidate(id);
+        }
+    }
+
+    // Maps the space of the group into the world, or the layer it is drawn on
+    pub fn world_transform(&self, id: GroupId) -> Mat3 {
+        let group = &self.groups[id.0 as usize];
+
+        if !group.stale {
+            return group.world.clone();
+        }
+
+        match group.parent {
+            Some(parent) => &self.world_transform(parent) * &group.transform,
+            None => group.transform.clone(),
+        }
+    }
+
+    pub fn group_of(&self, handle: PrimitiveHandle) -> Option<GroupId> {
+        self.entry(handle)?.group
+    }
+
+    // Primitives in a group are in its space rather than in world space
+    pub fn set_group(&mut self, handle: PrimitiveHandle, group: Option<GroupId>) {
+        let group = group.filter(|id| (id.0 as usize) < self.groups.len());
+
+        let entry = match self.entry_mut(handle) {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        let old_group = std::mem::replace(&mut entry.group, group);
+
+        if let Some(old_group) = old_group {
+            self.groups[old_group.0 as usize].members.remove(&handle.index);
+        }
+
+        if let Some(group) = group {
+            self.groups[group.0 as usize].members.insert(handle.index);
+        }
+
+        self.reindex(handle.index);
+    }
+
+    pub fn is_visible(&self, handle: PrimitiveHandle) -> bool {
+        self.entry(handle).is_some_and(|entry| entry.visible)
+    }
+
+    pub fn set_visible(&mut self, handle: PrimitiveHandle, visible: bool) {
+        if let Some(entry) = self.entry_mut(handle) {
+            entry.visible = visible;
+        }
+    }
+
+    pub fn set_z_index(&mut self, handle: PrimitiveHandle, z_index: i32) {
+        let entry = match self.entry_mut(handle) {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        let old_order = entry.order;
+        let new_order = OrderKey { z_index, ..old_order };
+
+        entry.primitive.z_index = z_index;
+        entry.order = new_order;
+
+        self.draw_order.remove(&old_order);
+        self.draw_order.insert(new_order, handle.index);
+    }
+
+    // Catches up with primitives changed through get_mut. Only the
+    // primitives handed out since the last update are looked at.
+    pub fn update(&mut self) {
+        if std::mem::take(&mut self.layers_dirty) {
+            self.reorder_layers();
+        }
+
+        if std::mem::take(&mut self.groups_dirty) {
+            self.update_groups();
+        }
+
+        for index in std::mem::take(&mut self.dirty) {
+            let entry = match self.slots[index as usize].entry.as_mut() {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            entry.dirty = false;
+
+            if entry.primitive.z_index != entry.order.z_index {
+                let old_order = entry.order;
+                let new_order = OrderKey { z_index: entry.primitive.z_index, ..old_order };
+
+                entry.order = new_order;
+
+                self.draw_order.remove(&old_order);
+                self.draw_order.insert(new_order, index);
+            }
+
+            self.reindex(index);
+        }
+    }
+
+    // Pixels that primitives may draw past their bounds, see Primitive::screen_reach
+    pub fn screen_reach(&self) -> f64 {
+        self.screen_reach
+    }
+
+    // Visible primitives on visible layers, back to front, as of the last
+    // draw order update
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (PrimitiveHandle, &Primitive)> {
+        self.draw_order.values().filter_map(move |&index| {
+            let slot = &self.slots[index as usize];
+            let entry = slot.entry.as_ref().filter(|entry| self.is_shown(entry))?;
+
+            Some((PrimitiveHandle { index, generation: slot.generation }, &entry.primitive))
+        })
+    }
+
+    // Visible primitives of a layer that may touch the area, back to front.
+    // The area is in the space of the layer, and primitives without bounds
+    // are always included.
+    pub fn iter_within(
+        &self,
+        layer: Option<LayerId>,
+        area: &Bounds,
+    ) -> impl DoubleEndedIterator<Item = (PrimitiveHandle, &Primitive)> {
+        let mut found = Vec::new();
+        self.index.query(area, &mut found);
+        found.extend(self.unbounded.iter().copied());
+
+        let mut ordered: Vec<(OrderKey, u32)> = found
+            .into_iter()
+            .filter_map(|index| {
+                let entry = self.slots[index as usize].entry.as_ref()?;
+                Some((entry.order, index)).filter(|_| entry.layer == layer && self.is_shown(entry))
+            })
+            .collect();
+
+        ordered.sort_unstable();
+
+        ordered.into_iter().map(move |(_, index)| {
+            let slot = &self.slots[index as usize];
+            let entry = slot.entry.as_ref().unwrap();
+
+            (PrimitiveHandle { index, generation: slot.generation }, &entry.primitive)
+        })
+    }
+
+    fn is_shown(&self, entry: &Entry) -> bool {
+        entry.visible && entry.layer.is_none_or(|id| self.layers[id.0 as usize].visible)
+    }
+
+    // Moves the primitives of layers whose z_index has changed. The
+    // primitives of a layer are next to each other in the draw order, so
+    // layers that only changed otherwise cost nothing.
+    fn reorder_layers(&mut self) {
+        for (id, layer) in self.layers.iter().enumerate() {
+            let old_z_index = self.layer_z_indices[id];
+
+            if layer.z_index == old_z_index {
+                continue;
+            }
+
+            let layer_key = |z_index: i32, sequence: u64| OrderKey {
+                layer_z_index: old_z_index,
+                layer: Some(LayerId(id as u32)),
+                z_index,
+                sequence,
+            };
+
+            let moved: Vec<(OrderKey, u32)> = self.draw_order
+                .range(layer_key(i32::MIN, 0)..=layer_key(i32::MAX, u64::MAX))
+                .map(|(&order, &index)| (order, index))
+                .collect();
+
+            for (old_order, index) in moved {
+                let new_order = OrderKey { layer_z_index: layer.z_index, ..old_order };
+
+                if let Some(entry) = self.slots[index as usize].entry.as_mut() {
+                    entry.order = new_order;
+                }
+
+                self.draw_order.remove(&old_order);
+                self.draw_order.insert(new_order, index);
+            }
+
+            self.layer_z_indices[id] = layer.z_index;
+        }
+    }
+
+    fn invalidate(&mut self, id: GroupId) {
+        let mut pending = vec![id];
+        self.groups_dirty = true;
+
+        while let Some(id) = pending.pop() {
+            let group = &mut self.groups[id.0 as usize];
+
+            // Everything below a stale group is stale already
+            if !group.stale {
+                group.stale = true;
+                pending.extend(group.children.iter().copied());
+            }
+        }
+    }
+
+    // Composes the stale world transforms and moves the primitives of
+    // those groups in the index
+    fn update_groups(&mut self) {
+        for index in 0..self.groups.len() {
+            if !self.groups[index].stale {
+                continue;
+            }
+
+            let world = match self.groups[index].parent {
+                Some(parent) => &self.groups[parent.0 as usize].world * &self.groups[index].transform,
+                None => self.groups[index].transform.clone(),
+            };
+
+            let group = &mut self.groups[index];
+            group.world = world;
+            group.stale = false;
+
+            for member in group.members.clone() {
+                self.reindex(member);
+            }
+        }
+    }
+
+    fn reindex(&mut self, index: u32) {
+        let entry = match self.slots[index as usize].entry.as_ref() {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        let primitive = &entry.primitive;
+
+        // Grouped primitives are indexed by the box around their transformed bounds
+        let bounds = match entry.group {
+            Some(group) => primitive.bounds().and_then(|bounds| {
+                let world = self.world_transform(group);
+
+                let corners = [
+                    &world * &vec3!(bounds.min_x, bounds.min_y, 1.0),
+                    &world * &vec3!(bounds.max_x, bounds.min_y, 1.0),
+                    &world * &vec3!(bounds.max_x, bounds.max_y, 1.0),
+                    &world * &vec3!(bounds.min_x, bounds.max_y, 1.0),
+                ];
+
+                Bounds::from_points(&corners)
+            }),
+            None => primitive.bounds(),
+        };
+
+        self.screen_reach = self.screen_reach.max(primitive.screen_reach());
+
+        match bounds {
+            Some(bounds) => {
+                self.unbounded.remove(&index);
+                self.index.insert(index, bounds);
+            }
+            None => {
+                self.index.remove(index);
+                self.unbounded.insert(index);
+            }
+        }
+    }
+
+    fn entry(&self, handle: PrimitiveHandle) -> Option<&Entry> {
+        let slot = self.slots.get(handle.index as usize)?;
+
+        if slot.generation != handle.generation {
+            return None;
+        }
+
+        slot.entry.as_ref()
+    }
+
+    fn entry_mut(&mut self, handle: PrimitiveHandle) -> Option<&mut Entry> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+
+        if slot.generation != handle.generation {
+            return None;
+        }
+
+        slot.entry.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::vec3::Vec3;
+    use crate::plotter::style::StrokeStyle;
+    use crate::plotter::Shape;
+    use crate::vec3;
+
+    fn point(x: f64, z_index: i32) -> Primitive {
+        Primitive {
+            shape: Shape::Point(vec3!(x, 0.0, 1.0)),
+            z_index,
+            stroke: None,
+            fill: None,
+            style: StrokeStyle::default(),
+        }
+    }
+
+    fn drawn(scene: &Scene) -> Vec<f64> {
+        scene.iter()
+            .map(|(_, primitive)| match primitive.shape {
+                Shape::Point(origin) => origin.x,
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_draw_order() {
+        let mut scene = Scene::new();
+
+        scene.add(point(1.0, 1));
+        scene.add(point(2.0, 0));
+        let third = scene.add(point(3.0, 1));
+        scene.add(point(4.0, 0));
+
+        assert_eq!(drawn(&scene), vec![2.0, 4.0, 1.0, 3.0]);
+
+        scene.set_z_index(third, -1);
+        assert_eq!(drawn(&scene), vec![3.0, 2.0, 4.0, 1.0]);
+
+        scene.set_visible(third, false);
+        assert_eq!(drawn(&scene), vec![2.0, 4.0, 1.0]);
+    }
+
+    #[test]
+    fn test_stale_handles() {
+        let mut scene = Scene::new();
+
+        let first = scene.add(point(1.0, 0));
+        assert!(scene.remove(first).is_some());
+
+        let second = scene.add(point(2.0, 0));
+
+        assert!(scene.get(first).is_none());
+        assert!(scene.remove(first).is_none());
+        assert!(scene.get(second).is_some());
+        assert_eq!(scene.len(), 1);
+    }
+
+    #[test]
+    fn test_z_index_changed_through_get_mut() {
+        let mut scene = Scene::new();
+
+        let first = scene.add(point(1.0, 0));
+        scene.add(point(2.0, 0));
+        let third = scene.add(point(3.0, 0));
+
+        scene.get_mut(first).unwrap().z_index = 1;
+        scene.get_mut(third).unwrap().z_index = 1;
+        scene.update();
+
+        assert_eq!(drawn(&scene), vec![2.0, 1.0, 3.0]);
+
+        scene.get_mut(first).unwrap().z_index = 0;
+        scene.remove(first);
+        scene.update();
+
+        assert_eq!(drawn(&scene), vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_many_points() {
+        let mut scene = Scene::new();
+
+        let handles: Vec<_> = (0..100_000)
+            .map(|i| scene.add(point(i as f64, i % 3)))
+            .collect();
+
+        for handle in handles.iter().step_by(2) {
+            scene.get_mut(*handle).unwrap().z_index += 10;
+        }
+
+        scene.update();
+
+        let order = drawn(&scene);
+
+        assert_eq!(order.len(), 100_000);
+        assert_eq!(&order[..3], &[3.0, 9.0, 15.0]);
+    }
+
+    #[test]
+    fn test_iter_within() {
+        let mut scene = Scene::new();
+
+        let near = scene.add(point(1.0, 1));
+        let far = scene.add(point(100.0, 0));
+        let line = scene.add(Primitive {
+            shape: Shape::Line(vec3!(0.0, 0.0, 1.0), vec3!(1.0, 1.0, 1.0)),
+            ..point(0.0, 2)
+        });
+
+        let area = Bounds::around(&vec3!(0.0, 0.0, 1.0), 10.0);
+        let within = |scene: &Scene| scene.iter_within(None, &area).map(|(handle, _)| handle).collect::<Vec<_>>();
+
+        assert_eq!(within(&scene), vec![near, line]);
+
+        // Moving a primitive only shows up after the update
+        if let Shape::Point(ref mut origin) = scene.get_mut(far).unwrap().shape {
+            origin.x = 5.0;
+        }
+
+        assert_eq!(within(&scene), vec![near, line]);
+
+        scene.update();
+        assert_eq!(within(&scene), vec![far, near, line]);
+
+        scene.remove(near);
+        scene.set_visible(line, false);
+        assert_eq!(within(&scene), vec![far]);
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut scene = Scene::new();
+
+        let ink = scene.add_layer(Layer::new("ink"));
+        let grid = scene.add_layer(Layer { z_index: -1, ..Layer::new("grid") });
+
+        let first = scene.add_to(Some(ink), point(1.0, 5));
+        scene.add(point(2.0, 10));
+        scene.add_to(Some(grid), point(3.0, 100));
+        scene.add_to(Some(ink), point(4.0, -5));
+
+        // Primitives only compete on z_index within their layer
+        assert_eq!(scene.layers_in_order(), vec![Some(grid), None, Some(ink)]);
+        assert_eq!(drawn(&scene), vec![3.0, 2.0, 4.0, 1.0]);
+        assert_eq!(scene.layer_of(first), Some(ink));
+        assert_eq!(scene.find_layer("grid"), Some(grid));
+
+        scene.layer_mut(grid).unwrap().z_index = 1;
+        scene.update();
+        assert_eq!(drawn(&scene), vec![2.0, 4.0, 1.0, 3.0]);
+
+        // Primitives added before the update move along with their layer
+        scene.layer_mut(ink).unwrap().z_index = 2;
+        scene.add_to(Some(ink), point(5.0, 0));
+        scene.layer_mut(grid).unwrap().opacity = 0.5;
+        scene.update();
+        assert_eq!(drawn(&scene), vec![2.0, 3.0, 4.0, 5.0, 1.0]);
+
+        scene.layer_mut(ink).unwrap().visible = false;
+        assert_eq!(drawn(&scene), vec![2.0, 3.0]);
+        assert_eq!(scene.layers_in_order(), vec![None, Some(grid)]);
+
+        let area = Bounds::around(&vec3!(0.0, 0.0, 1.0), 10.0);
+        let within: Vec<_> = scene.iter_within(Some(grid), &area).collect();
+        assert_eq!(within.len(), 1);
+    }
+
+    #[test]
+    fn test_groups() {
+        let mut scene = Scene::new();
+
+        let arm = scene.add_group(None, Mat3::identity().translate(&vec3!(100.0, 0.0, 1.0)));
+        let hand = scene.add_group(Some(arm), Mat3::identity().translate(&vec3!(10.0, 0.0, 1.0)));
+
+        let finger = scene.add(point(1.0, 0));
+        scene.set_group(finger, Some(hand));
+        scene.update();
+
+        let near = |scene: &Scene, x: f64| {
+            let area = Bounds::around(&vec3!(x, 0.0, 1.0), 0.5);
+            scene.iter_within(None, &area).map(|(handle, _)| handle).collect::<Vec<_>>()
+        };
+
+        assert_eq!(&scene.world_transform(hand) * &vec3!(1.0, 0.0, 1.0), vec3!(111.0, 0.0, 1.0));
+        assert_eq!(near(&scene, 111.0), vec![finger]);
+
+        // Moving the parent moves everything below it
+        scene.set_group_transform(arm, Mat3::identity().translate(&vec3!(200.0, 0.0, 1.0)));
+
+        assert_eq!(&scene.world_transform(hand) * &vec3!(1.0, 0.0, 1.0), vec3!(211.0, 0.0, 1.0));
+
+        scene.update();
+        assert!(near(&scene, 111.0).is_empty());
+        assert_eq!(near(&scene, 211.0), vec![finger]);
+
+        scene.set_group(finger, None);
+        assert_eq!(near(&scene, 1.0), vec![finger]);
+        assert_eq!(scene.group_of(finger), None);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::common::colors::Color;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Fill {
+    pub color: Color,
+    pub rule: FillRule,
+}
+
+// World widths are scaled by the transform, screen widths stay in pixels
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LineWidth {
+    Screen(f64),
+    World(f64),
+}
+
+impl LineWidth {
+    pub fn value(&self) -> f64 {
+        match *self {
+            LineWidth::Screen(width) | LineWidth::World(width) => width,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct StrokeStyle {
+    pub width: LineWidth,
+    // Dash lengths and offset are measured in the same units as the width
+    pub dash: Vec<f64>,
+    pub dash_offset: f64,
+    pub cap: LineCap,
+    pub join: LineJoin,
+    pub miter_limit: f64,
+}
+
+impl Default for StrokeStyle {
+    // Same as the canvas defaults
+    fn default() -> Self {
+        StrokeStyle {
+            width: LineWidth::Screen(1.0),
+            dash: Vec::new(),
+            dash_offset: 0.0,
+            cap: LineCap::Butt,
+            join: LineJoin::Miter,
+            miter_limit: 10.0,
+        }
+    }
+}
+
+impl StrokeStyle {
+    pub fn with_width(width: LineWidth) -> Self {
+        StrokeStyle {
+            width,
+            ..StrokeStyle::default()
+        }
+    }
+
+    // How far the stroke may reach past its path, in the units of its width.
+    // Miter joins are the worst case, up to the miter limit.
+    pub fn reach(&self) -> f64 {
+        let join = if self.join == LineJoin::Miter { self.miter_limit.max(1.0) } else { 1.0 };
+        let cap = if self.cap == LineCap::Square { std::f64::consts::SQRT_2 } else { 1.0 };
+
+        self.width.value() / 2.0 * f64::max(join, cap)
+    }
+
+    // Same style with the width and dashes converted to pixels
+    pub fn to_screen(&self, transform_scale: f64) -> Self {
+        match self.width {
+            LineWidth::Screen(_) => self.clone(),
+            LineWidth::World(width) => StrokeStyle {
+                width: LineWidth::Screen(width * transform_scale),
+                dash: self.dash.iter().map(|length| length * transform_scale).collect(),
+                dash_offset: self.dash_offset * transform_scale,
+                ..self.clone()
+            },
+        }
+    }
+}
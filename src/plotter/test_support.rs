@@ -0,0 +1,25 @@
+// Fixtures shared by the tests of the plotter and of what builds on it
+
+use crate::common::colors::Color;
+
+use super::backend::recording::RecordingBackend;
+use super::style::StrokeStyle;
+use super::{Plotter, Primitive, Shape};
+
+// 100 by 100 pixels, with the identity transform
+pub fn plotter() -> Plotter<RecordingBackend> {
+    let mut plotter = Plotter::with_backend(RecordingBackend::new(100.0, 100.0));
+    plotter.update_canvas_size();
+    plotter
+}
+
+// Stroked in black, at z_index 0
+pub fn primitive(shape: Shape) -> Primitive {
+    Primitive {
+        shape,
+        z_index: 0,
+        stroke: Some(Color::rgb(0.0, 0.0, 0.0)),
+        fill: None,
+        style: StrokeStyle::default(),
+    }
+}
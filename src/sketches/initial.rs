@@ -1,11 +1,21 @@
 use std::f64;
 
+use crate::animation::{Channel, Repeat, Timeline};
+use crate::animation::easing::Easing;
+use crate::animation::track::Track;
 use crate::interop::log::*;
 use crate::plotter::{
+    Grid,
     Plotter,
     Primitive,
     Shape,
 };
+use crate::plotter::backend::Backend;
+use crate::plotter::backend::canvas::CanvasBackend;
+use crate::plotter::camera::Camera;
+use crate::plotter::layer::{Layer, LayerId};
+use crate::plotter::scene::PrimitiveHandle;
+use crate::plotter::style::StrokeStyle;
 use crate::math::vec3::Vec3;
 use crate::interop::events::*;
 use crate::interop::events::GlobalEvent::*;
@@ -13,16 +23,26 @@ use crate::vec3;
 
 use crate::common::colors::*;
 
-pub struct Initial {
-    plotter: Plotter,
-    point: usize,
-    segment: usize
+use super::Sketch;
+
+pub struct Initial<B: Backend = CanvasBackend> {
+    plotter: Plotter<B>,
+    camera: Camera,
+    timeline: Timeline,
+    ink: LayerId,
+    segment: PrimitiveHandle,
+    // Points added by clicking, in the order they were placed
+    placed: Vec<PrimitiveHandle>,
 }
 
 impl Initial {
     pub fn new(canvas: web_sys::HtmlCanvasElement) -> Self {
-        let mut p: Plotter = Plotter::new(canvas);
+        Initial::with_plotter(Plotter::new(canvas))
+    }
+}
 
+impl<B: Backend> Initial<B> {
+    pub fn with_plotter(mut p: Plotter<B>) -> Self {
         p.set_transform(
             p.get_transform()
                 .translate(&vec3!(120.0, 130.0, 1.0))
@@ -31,68 +51,113 @@ impl Initial {
 
         p.set_clear_color(&PAPER);
 
-        p.add_primitive(Primitive {
-            shape: Shape::Grid(50.0),
+        let grid = p.add_layer(Layer { z_index: -1, ..Layer::new("grid") });
+        let construction = p.add_layer(Layer { opacity: 0.6, ..Layer::new("construction") });
+        let ink = p.add_layer(Layer { z_index: 1, ..Layer::new("ink") });
+
+        p.add_primitive_to(grid, Primitive {
+            shape: Shape::Grid(Grid {
+                step: 250.0,
+                subdivisions: 5,
+                adaptive: true,
+                minor_color: Some(LIGHT_BLUE_INK),
+                axis_color: Some(RED),
+            }),
             z_index: 0,
-            color: LIGHT_BLUE_INK.as_rgb_string().into(),
-        });
-
-        p.add_primitive(Primitive {
-            shape: Shape::Grid(250.0),
-            z_index: 1,
-            color: BLUE_INK.as_rgb_string().into(),
+            stroke: Some(BLUE_INK),
+            fill: None,
+            style: StrokeStyle::default(),
         });
 
         // p.set_transform(p.get_transform().translate(&Vec3 { x: 10.0, y: 20.0, z: 1.0 }));
-        let point = p.add_primitive(Primitive {
-            shape: Shape::Point(Vec3::from_values(100.0, 100.0, 1.0)),
-            z_index: 2,
-            color: RED.as_rgb_string().into(),
-        });
-
-        let segment = p.add_primitive(Primitive {
+        let segment = p.add_primitive_to(construction, Primitive {
             shape: Shape::Segment(
                 Vec3::from_values(100.0, 100.0, 1.0),
                 Vec3::from_values(0.0, 0.0, 1.0)
             ),
             z_index: 1,
-            color: GREEN.as_rgb_string().into(),
+            stroke: Some(GREEN),
+            fill: None,
+            style: StrokeStyle::default(),
         });
 
-        let result: Initial = Initial {
+        let point = p.add_primitive_to(ink, Primitive {
+            shape: Shape::Point(vec3!(0.0, 100.0, 1.0)),
+            z_index: 2,
+            stroke: Some(RED),
+            fill: None,
+            style: StrokeStyle::default(),
+        });
+
+        // The point swings back and forth, dragging the end of the segment along
+        let swing = Track::new(vec3!(0.0, 100.0, 1.0))
+            .then(2.0, vec3!(200.0, 100.0, 1.0), Easing::SineInOut);
+        let mut timeline = Timeline::new();
+
+        timeline.at(0.0, Channel::Position(point, swing.clone())).repeat(Repeat::Forever).ping_pong();
+        timeline.with(Channel::Vertex(segment, 0, swing)).repeat(Repeat::Forever).ping_pong();
+        timeline
+            .with(Channel::Stroke(point, Track::new(RED).then(2.0, BLUE_INK, Easing::Linear)))
+            .repeat(Repeat::Forever)
+            .ping_pong();
+
+        // The middle button moves the view, the others place and remove points
+        let mut camera = Camera::new();
+        camera.button = 1;
+
+        let result = Initial {
             plotter: p,
-            point,
+            camera,
+            timeline,
+            ink,
             segment,
+            placed: Vec::new(),
         };
 
         return result
     }
 
     fn on_tick(&mut self, data: &TickEventData) {
-        self.plotter.update_canvas_size();
-
-        let point = self.plotter.get_mut(self.point);
-
-        if let Shape::Point(ref mut pos) = point.shape {
-            (*pos).x = 100.0 + f64::sin(data.time) * 100.0;
-        }
-
+        self.timeline.update(data.time, &mut self.plotter);
         self.plotter.render();
     }
 
     fn on_pointer_down(&mut self, data: &PointerEventData) {
-        let p = self.plotter.project_to_canvas(&vec3!(data.x, data.y, 1.0));
+        // Right click takes back the last placed point
+        if data.button == 2 {
+            if let Some(handle) = self.placed.pop() {
+                self.plotter.remove_primitive(handle);
+            }
+
+            return;
+        }
 
-        self.plotter.add_primitive(Primitive {
+        let p = self.plotter.unproject_from_canvas(&vec3!(data.x, data.y, 1.0));
+
+        let handle = self.plotter.add_primitive_to(self.ink, Primitive {
             shape: Shape::Point(p),
             z_index: 2,
-            color: RED.as_rgb_string().into(),
+            stroke: Some(RED),
+            fill: None,
+            style: StrokeStyle::default(),
         });
+
+        self.placed.push(handle);
     }
 }
 
-impl EventListener for Initial {
+impl<B: Backend> Sketch<B> for Initial<B> {
+    fn plotter_mut(&mut self) -> &mut Plotter<B> {
+        &mut self.plotter
+    }
+}
+
+impl<B: Backend> EventListener for Initial<B> {
     fn dispatch(&mut self, event: &GlobalEvent) {
+        if self.camera.dispatch(event, &mut self.plotter) {
+            return;
+        }
+
         match event {
             Tick(ref data) => self.on_tick(data),
             PointerDown(ref data) => self.on_pointer_down(data),
@@ -1,28 +1,121 @@
-use std::borrow::BorrowMut;
-use std::cell::{RefCell, RefMut};
+use std::cell::RefCell;
+use std::rc::Rc;
 
 use crate::interop::events::*;
 use crate::interop::log::*;
+use crate::plotter::backend::Backend;
+use crate::plotter::backend::canvas::CanvasBackend;
+use crate::plotter::backend::raster::RasterBackend;
+use crate::plotter::pen::gcode::GcodeOptions;
+use crate::plotter::pen::{gcode, hpgl, Plot, PlotOptions};
+use crate::plotter::Plotter;
 
 pub mod initial;
 
-pub struct Sketchbook {}
+pub trait Sketch<B: Backend = CanvasBackend>: EventListener {
+    fn plotter_mut(&mut self) -> &mut Plotter<B>;
+}
+
+// Sketch by the name in the page URL, with or without the leading #
+fn create<B: Backend + 'static>(name: &str, plotter: Plotter<B>) -> Option<Box<dyn Sketch<B>>> {
+    match name.trim_start_matches('#') {
+        "initial" => Some(Box::new(initial::Initial::with_plotter(plotter))),
+        _ => None
+    }
+}
+
+// PNG of a sketch as it looks `time` seconds after it starts, rendered
+// without a browser, e.g. for regression images
+pub fn render_png(name: &str, width: usize, height: usize, time: f64) -> Option<Vec<u8>> {
+    let mut sketch = create(name, Plotter::with_backend(RasterBackend::new(width, height)))?;
+    let mut clock = FrameClock::new();
+
+    sketch.plotter_mut().resize(width as f64, height as f64, 1.0);
+    sketch.dispatch(&GlobalEvent::Tick(clock.tick(0.0)));
+
+    if time > 0.0 {
+        sketch.dispatch(&GlobalEvent::Tick(clock.tick(time)));
+    }
+
+    Some(sketch.plotter_mut().export_png())
+}
+
+// The bus owns its listeners, so the active sketch is shared with it
+struct SketchListener(Rc<RefCell<Box<dyn Sketch>>>);
+
+impl EventListener for SketchListener {
+    fn dispatch(&mut self, event: &GlobalEvent) {
+        let mut sketch = self.0.borrow_mut();
+
+        // Every sketch fills the window
+        if let GlobalEvent::WindowResize(data) = event {
+            sketch.plotter_mut().resize(data.width, data.height, data.pixel_ratio);
+        }
+
+        sketch.dispatch(event);
+    }
+}
+
+#[derive(Default)]
+pub struct Sketchbook {
+    active: Option<Rc<RefCell<Box<dyn Sketch>>>>,
+}
 
 impl Sketchbook {
     pub fn new() -> Self {
-        Sketchbook {}
+        Sketchbook {
+            active: None,
+        }
     }
 
     pub fn load_sketch(&mut self, args: String, canvas: web_sys::HtmlCanvasElement) -> () {
         log(&format!("Loading sketch '{}'...", &args));
 
-        let sketch = match args.as_str() {
-            "#initial" => Some(initial::Initial::new(canvas)),
-            _ => None
-        };
+        let sketch = create(&args, Plotter::new(canvas)).map(|sketch| Rc::new(RefCell::new(sketch)));
 
         if let Some(sketch) = sketch {
-            attach_global_listener(Box::new(sketch));
+            attach_global_listener(Box::new(SketchListener(sketch.clone())));
+            self.active = Some(sketch);
         }
     }
+
+    pub fn export_svg(&self) -> Option<String> {
+        self.active
+            .as_ref()
+            .map(|sketch| sketch.borrow_mut().plotter_mut().export_svg())
+    }
+
+    pub fn export_hpgl(&self) -> Option<String> {
+        self.plot(&PlotOptions::default()).map(|plot| hpgl::export(&plot.paths))
+    }
+
+    pub fn export_gcode(&self) -> Option<String> {
+        let options = GcodeOptions::default();
+        self.plot(&options.plot).map(|plot| gcode::export(&plot.paths, &options))
+    }
+
+    fn plot(&self, options: &PlotOptions) -> Option<Plot> {
+        let plot = self.active.as_ref()?.borrow_mut().plotter_mut().plot(options);
+
+        log(&format!(
+            "Pen travel: {:.0} mm, down from {:.0} mm",
+            plot.travel.after,
+            plot.travel.before,
+        ));
+
+        Some(plot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_png() {
+        let png = render_png("#initial", 64, 48, 0.5).unwrap();
+
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert!(render_png("missing", 64, 48, 0.0).is_none());
+    }
 }